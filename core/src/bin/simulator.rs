@@ -0,0 +1,230 @@
+// Host simulator of the whole hourglass: the real logic, display and sensor tasks run against
+// the emulated transports, the led matrices are drawn in the terminal and the virtual device
+// is tilted with the keyboard.
+//
+// usage: cargo run --features emulator --bin simulator -- [duration in seconds] [continue|restart]
+
+use std::io::{BufRead, Write};
+use std::time::Duration;
+use async_channel::{Receiver, Sender};
+use futures_timer::Delay;
+use led_hourglass_core::logic::*;
+use led_hourglass_core::max7219::*;
+use led_hourglass_core::mpu6050::*;
+
+
+// number of cascaded MAX7219 led matrices
+const MAX7219_DEVICES: usize = 2;
+const CHAMBER_SIZE: usize = 8;
+const RENDER_PERIOD: Duration = Duration::from_millis(50);
+// the sensor task calibrates itself lying flat, the device is stood up afterwards
+const CALIBRATION_TIME: Duration = Duration::from_secs(3);
+const TILT_STEP: f32 = 15f32;
+const TILT_RATE: f32 = 150f32;
+const FLIP_RATE: f32 = 360f32;
+const STAND_RATE: f32 = 180f32;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Control {
+    TiltLeft,
+    TiltRight,
+    Flip,
+    Upright,
+    LayFlat,
+}
+
+
+// hourglass axis in the sensor frame, the logic sees gravity (acc.y, -acc.x) on the display
+// and the upper chamber is on top when it points along the display diagonal
+fn upright_gravity() -> (f32, f32, f32) {
+    (-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2, 0f32)
+}
+
+// rotation around the axis perpendicular to the displays, positive turns the device clockwise
+fn turn(angle: f32, rate: f32) -> MotionStep {
+    MotionStep {
+        rotation: (0f32, 0f32, rate * angle.signum()),
+        duration: Duration::from_secs_f32(angle.abs() / rate),
+    }
+}
+
+// shortest rotation which turns the measured gravity into the target one, the device is moved
+// instead of setting the gravity directly so the gyroscope sees the motion as well
+fn turn_to(gravity: (f32, f32, f32), target: (f32, f32, f32), rate: f32) -> MotionStep {
+    // dg/dt = g x w, so rotating around target x g moves g towards the target
+    let axis = (
+        target.1 * gravity.2 - target.2 * gravity.1,
+        target.2 * gravity.0 - target.0 * gravity.2,
+        target.0 * gravity.1 - target.1 * gravity.0,
+    );
+    let len = (axis.0 * axis.0 + axis.1 * axis.1 + axis.2 * axis.2).sqrt();
+    if len < 1e-6 {
+        return MotionStep { rotation: (0f32, 0f32, 0f32), duration: Duration::ZERO };
+    }
+    let dot = gravity.0 * target.0 + gravity.1 * target.1 + gravity.2 * target.2;
+    let angle = dot.clamp(-1f32, 1f32).acos().to_degrees();
+    MotionStep {
+        rotation: (axis.0 * rate / len, axis.1 * rate / len, axis.2 * rate / len),
+        duration: Duration::from_secs_f32(angle / rate),
+    }
+}
+
+async fn control_task(sensor: Mpu6050Emulator, controls: Receiver<Control>) {
+    Delay::new(CALIBRATION_TIME).await;
+    let mut next = Some(Control::Upright);
+
+    loop {
+        let control = match next.take() {
+            Some(control) => control,
+            None => match controls.recv().await {
+                Ok(control) => control,
+                Err(_) => return,
+            },
+        };
+        let step = match control {
+            Control::TiltLeft => turn(-TILT_STEP, TILT_RATE),
+            Control::TiltRight => turn(TILT_STEP, TILT_RATE),
+            Control::Flip => turn(180f32, FLIP_RATE),
+            Control::Upright => turn_to(sensor.gravity(), upright_gravity(), STAND_RATE),
+            Control::LayFlat => turn_to(sensor.gravity(), (0f32, 0f32, 1f32), STAND_RATE),
+        };
+        // a new script replaces the running one, so wait until the device stops turning
+        sensor.set_script(vec![step]);
+        Delay::new(step.duration).await;
+    }
+}
+
+// stdin is blocking, so keys are read on a separate thread and passed to the control task
+fn keyboard_thread(controls: Sender<Control>) {
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        for key in line.chars() {
+            let control = match key {
+                'a' => Control::TiltLeft,
+                'd' => Control::TiltRight,
+                'f' => Control::Flip,
+                'u' => Control::Upright,
+                'l' => Control::LayFlat,
+                'q' => {
+                    print!("\x1b[?25h");
+                    std::process::exit(0);
+                }
+                _ => continue,
+            };
+            if controls.send_blocking(control).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// tilt of the hourglass axis from upright in degrees, None when the device lies flat
+fn tilt(gravity: (f32, f32, f32)) -> Option<f32> {
+    let display = (gravity.1, -gravity.0);
+    if display.0.hypot(display.1) < 0.2 {
+        return None;
+    }
+    let angle = (display.0 - display.1).atan2(display.0 + display.1).to_degrees();
+    Some(angle)
+}
+
+fn render(display: &Max7219Emulator, sensor: &Mpu6050Emulator) -> String {
+    let upper = display.chip(UPPER_DISPLAY);
+    let lower = display.chip(LOWER_DISPLAY);
+    let upper_rows = upper.visible_rows();
+    let lower_rows = lower.visible_rows();
+
+    // chambers are placed corner to corner, the neck is in the middle of the picture
+    let mut out = String::from("\x1b[H led hourglass simulator\r\n\r\n");
+    for y in 0..2 * CHAMBER_SIZE {
+        out.push_str("  ");
+        for x in 0..2 * CHAMBER_SIZE {
+            let cell = if x < CHAMBER_SIZE && y < CHAMBER_SIZE {
+                Some(upper_rows[y] & (1 << x) != 0)
+            } else if x >= CHAMBER_SIZE && y >= CHAMBER_SIZE {
+                Some(lower_rows[y - CHAMBER_SIZE] & (1 << (x - CHAMBER_SIZE)) != 0)
+            } else {
+                None
+            };
+            out.push_str(match cell {
+                Some(true) => "██",
+                Some(false) => " ·",
+                None => "  ",
+            });
+        }
+        out.push_str("\r\n");
+    }
+
+    let gravity = sensor.gravity();
+    let tilt = match tilt(gravity) {
+        Some(angle) => format!("{:+4.0}°", angle),
+        None => String::from("flat"),
+    };
+    out.push_str(&format!("\r\n tilt: {:<6} intensity: {:>2} / {:<2}\x1b[K\r\n", tilt, upper.intensity, lower.intensity));
+    out.push_str(" keys + enter: a/d tilt, f flip, u upright, l lay flat, q quit\x1b[K\r\n\x1b[K");
+    out
+}
+
+async fn render_task(display: Max7219Emulator, sensor: Mpu6050Emulator) {
+    print!("\x1b[2J\x1b[?25l");
+    loop {
+        let frame = render(&display, &sensor);
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(frame.as_bytes()).unwrap();
+        stdout.flush().unwrap();
+        drop(stdout);
+        Delay::new(RENDER_PERIOD).await;
+    }
+}
+
+
+async fn app(logic_config: LogicConfig) {
+    let display = Max7219Emulator::new(MAX7219_DEVICES);
+    let sensor = Mpu6050Emulator::new();
+
+    // create communication channels between tasks
+    let (acc_server, acc_observer) = async_channel::unbounded::<Mpu6050ObserverData>();
+    let (led_matrix_client, led_matrix_server) = async_channel::unbounded::<Max7219Action>();
+    let (control_client, control_server) = async_channel::unbounded::<Control>();
+
+    std::thread::spawn(move || keyboard_thread(control_client));
+
+    let max7219_config: Max7219Config<MAX7219_DEVICES> = Max7219Config::default();
+    // same sensor setup as on the device
+    let mpu6050_config = Mpu6050Config {
+        accel_range: AccelRange::G2,
+        gyro_range: GyroRange::Deg1000,
+        dlpf: DlpfBandwidth::Hz44,
+        sample_rate_divider: 9,
+        fifo: true,
+        filter: OrientationFilter::Madgwick { beta: 0.5 },
+        calibration_pause: Duration::from_secs(2),
+    };
+
+    futures::join!(
+        logic_task(acc_observer, led_matrix_client, logic_config),
+        max7219_task(display.clone(), Some(led_matrix_server), max7219_config),
+        mpu6050_task(sensor.clone(), Some(sensor.interrupt_pin()), Some(acc_server), mpu6050_config),
+        control_task(sensor.clone(), control_server),
+        render_task(display, sensor),
+    );
+}
+
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let duration = args.next()
+        .map(|arg| arg.parse().expect("duration has to be a number of seconds"))
+        .unwrap_or(60);
+    let flip_mode = match args.next().as_deref() {
+        None | Some("continue") => FlipMode::Continue,
+        Some("restart") => FlipMode::Restart,
+        Some(mode) => panic!("unknown flip mode {}, use continue or restart", mode),
+    };
+
+    let logic_config = LogicConfig { duration: Duration::from_secs(duration), flip_mode };
+    futures::executor::block_on(app(logic_config));
+}
@@ -0,0 +1,15 @@
+use std::fmt::Debug;
+
+
+#[allow(async_fn_in_trait)]
+pub trait I2cTransportInterface {
+    type Error: Debug;
+
+    // read up to output len
+    async fn write_read(&mut self, address: u8, data_to_write: &[u8], output: &mut [u8]) -> Result<(), Self::Error>;
+
+    async fn write(&mut self, address: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    // read up to output len
+    async fn read(&mut self, address: u8, output: &mut [u8]) -> Result<(), Self::Error>;
+}
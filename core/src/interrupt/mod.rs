@@ -0,0 +1,10 @@
+use std::fmt::Debug;
+
+
+#[allow(async_fn_in_trait)]
+pub trait InterruptPinInterface {
+    type Error: Debug;
+
+    // completes on the next active edge of the pin
+    async fn wait_for_interrupt(&mut self) -> Result<(), Self::Error>;
+}
@@ -0,0 +1,9 @@
+// Hardware independent part of the led hourglass: simulation logic, display and sensor
+// drivers working on top of the transport traits. ESP specific glue lives in the binary.
+
+pub mod spi;
+pub mod i2c;
+pub mod interrupt;
+pub mod logic;
+pub mod max7219;
+pub mod mpu6050;
@@ -0,0 +1,55 @@
+use std::time::Duration;
+use crate::max7219::{Animation, PlayMode};
+use super::{UPPER_DISPLAY, LOWER_DISPLAY};
+
+
+fn chamber_frames(upper: [u8; 8], lower: [u8; 8]) -> Vec<[u8; 8]> {
+    let mut devices = vec![[0u8; 8]; 2];
+    devices[UPPER_DISPLAY] = upper;
+    devices[LOWER_DISPLAY] = lower;
+    devices
+}
+
+// leds (x, y) with x + y <= diagonal lit
+fn fill_to_diagonal(diagonal: u8) -> [u8; 8] {
+    let mut rows = [0u8; 8];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for x in 0..8 {
+            if x + y as u8 <= diagonal {
+                *row |= 1 << x;
+            }
+        }
+    }
+    rows
+}
+
+// outline of the square with given distance from the matrix center
+fn square(radius: u8) -> [u8; 8] {
+    let (min, max) = (3 - radius, 4 + radius);
+    let mut rows = [0u8; 8];
+    for (y, row) in rows.iter_mut().enumerate() {
+        let y = y as u8;
+        for x in min..=max {
+            if (y == min || y == max) || ((min..=max).contains(&y) && (x == min || x == max)) {
+                *row |= 1 << x;
+            }
+        }
+    }
+    rows
+}
+
+
+// upper chamber filling up from its far corner down to the neck
+pub fn boot_splash() -> Animation {
+    let animation = (0..15).fold(Animation::new(PlayMode::Once), |animation, diagonal| {
+        animation.frame(chamber_frames(fill_to_diagonal(diagonal), [0; 8]), Duration::from_millis(60))
+    });
+    animation.frame(chamber_frames([0xFF; 8], [0; 8]), Duration::from_millis(500))
+}
+
+// squares pulsing in both chambers, shown when the countdown is over
+pub fn time_up() -> Animation {
+    (0..4).fold(Animation::new(PlayMode::PingPong), |animation, radius| {
+        animation.frame(chamber_frames(square(radius), square(3 - radius)), Duration::from_millis(120))
+    })
+}
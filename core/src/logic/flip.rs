@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+use super::geometry::Chamber;
+
+// gravity along the hourglass axis (in g) pointing towards the top chamber which starts a flip
+const FLIP_THRESHOLD: f32 = 0.5f32;
+// below this value a started flip is cancelled, the gap to FLIP_THRESHOLD is the hysteresis
+const RELEASE_THRESHOLD: f32 = 0.3f32;
+// time the device has to stay upside down before the flip is accepted
+const DEBOUNCE_TIME: Duration = Duration::from_millis(300);
+
+
+// what happens to the countdown after the hourglass was turned upside down
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlipMode {
+    // like a real hourglass, grains which already fell are the time left to run
+    Continue,
+    // all grains are put back into the new top chamber and the countdown starts again
+    Restart,
+}
+
+
+// Detects turning the hourglass upside down from the gravity vector.
+pub struct FlipDetector {
+    top: Chamber,
+    flip_start: Option<Instant>,
+}
+
+impl FlipDetector {
+
+    pub fn new(top: Chamber) -> Self {
+        Self {
+            top,
+            flip_start: None,
+        }
+    }
+
+    // axis is gravity projected on the direction from upper to lower chamber,
+    // returns new top chamber when a flip gets confirmed
+    pub fn update(&mut self, axis: f32, now: Instant) -> Option<Chamber> {
+        // gravity pointing towards the current top chamber
+        let upwards = match self.top {
+            Chamber::Upper => -axis,
+            Chamber::Lower => axis,
+        };
+
+        match self.flip_start {
+            None if upwards > FLIP_THRESHOLD => {
+                self.flip_start = Some(now);
+            }
+            Some(_) if upwards < RELEASE_THRESHOLD => {
+                self.flip_start = None;
+            }
+            _ => {}
+        }
+
+        if let Some(start) = self.flip_start {
+            if now.saturating_duration_since(start) >= DEBOUNCE_TIME {
+                self.flip_start = None;
+                self.top = self.top.other();
+                return Some(self.top);
+            }
+        }
+
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // gravity pointing from the lower to the upper chamber, the upper one is at the bottom
+    const UPSIDE_DOWN: f32 = -0.6f32;
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn flip_is_debounced() {
+        let start = Instant::now();
+        let mut detector = FlipDetector::new(Chamber::Upper);
+        assert_eq!(detector.update(UPSIDE_DOWN, start), None);
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 299)), None);
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 300)), Some(Chamber::Lower));
+
+        // the lower chamber is on top now, flipping back needs the opposite direction
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 1000)), None);
+        assert_eq!(detector.update(-UPSIDE_DOWN, after(start, 1000)), None);
+        assert_eq!(detector.update(-UPSIDE_DOWN, after(start, 1300)), Some(Chamber::Upper));
+    }
+
+    #[test]
+    fn started_flip_survives_values_above_release_threshold() {
+        let start = Instant::now();
+        let mut detector = FlipDetector::new(Chamber::Upper);
+        detector.update(UPSIDE_DOWN, start);
+        assert_eq!(detector.update(-0.4f32, after(start, 100)), None);
+        assert_eq!(detector.update(-0.4f32, after(start, 300)), Some(Chamber::Lower));
+    }
+
+    #[test]
+    fn flip_below_start_threshold_is_ignored() {
+        let start = Instant::now();
+        let mut detector = FlipDetector::new(Chamber::Upper);
+        assert_eq!(detector.update(-0.4f32, start), None);
+        assert_eq!(detector.update(-0.4f32, after(start, 500)), None);
+    }
+
+    #[test]
+    fn flip_is_cancelled_below_release_threshold() {
+        let start = Instant::now();
+        let mut detector = FlipDetector::new(Chamber::Upper);
+        detector.update(UPSIDE_DOWN, start);
+        assert_eq!(detector.update(-0.2f32, after(start, 100)), None);
+        // debounce starts again from the next value above the flip threshold
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 350)), None);
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 600)), None);
+        assert_eq!(detector.update(UPSIDE_DOWN, after(start, 650)), Some(Chamber::Lower));
+    }
+}
@@ -0,0 +1,78 @@
+// edge length of one chamber, equal to the size of one led matrix
+pub const CHAMBER_SIZE: u8 = 8;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Chamber {
+    Upper,
+    Lower,
+}
+
+impl Chamber {
+    pub fn other(self) -> Chamber {
+        match self {
+            Chamber::Upper => Chamber::Lower,
+            Chamber::Lower => Chamber::Upper,
+        }
+    }
+}
+
+
+// Two led matrices placed corner to corner like the bulbs of an hourglass.
+// In global coordinates the upper chamber covers (0..8, 0..8) and the lower one (8..16, 8..16),
+// so the only pair of neighbouring cells across both chambers is the neck: (7, 7) <-> (8, 8).
+#[derive(Clone, Copy)]
+pub struct HourglassGeometry;
+
+impl HourglassGeometry {
+
+    pub fn width(&self) -> u8 {
+        2 * CHAMBER_SIZE
+    }
+
+    pub fn height(&self) -> u8 {
+        2 * CHAMBER_SIZE
+    }
+
+    pub fn chamber(&self, x: i32, y: i32) -> Option<Chamber> {
+        let size = CHAMBER_SIZE as i32;
+        if (0..size).contains(&x) && (0..size).contains(&y) {
+            Some(Chamber::Upper)
+        } else if (size..2 * size).contains(&x) && (size..2 * size).contains(&y) {
+            Some(Chamber::Lower)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_cell(&self, x: i32, y: i32) -> bool {
+        self.chamber(x, y).is_some()
+    }
+
+    // neck cell of given chamber, the corner touching the other chamber
+    pub fn neck(&self, chamber: Chamber) -> (u8, u8) {
+        match chamber {
+            Chamber::Upper => (CHAMBER_SIZE - 1, CHAMBER_SIZE - 1),
+            Chamber::Lower => (CHAMBER_SIZE, CHAMBER_SIZE),
+        }
+    }
+
+    // global coordinates of the top left cell of the chamber
+    pub fn origin(&self, chamber: Chamber) -> (u8, u8) {
+        match chamber {
+            Chamber::Upper => (0, 0),
+            Chamber::Lower => (CHAMBER_SIZE, CHAMBER_SIZE),
+        }
+    }
+
+    // all cells of the chamber, the ones farthest from the neck first
+    pub fn cells_from_far_end(&self, chamber: Chamber) -> Vec<(u8, u8)> {
+        let (ox, oy) = self.origin(chamber);
+        let (nx, ny) = self.neck(chamber);
+        let mut cells: Vec<(u8, u8)> = (0..CHAMBER_SIZE)
+            .flat_map(|y| (0..CHAMBER_SIZE).map(move |x| (ox + x, oy + y)))
+            .collect();
+        cells.sort_by_key(|(x, y)| std::cmp::Reverse(x.abs_diff(nx) + y.abs_diff(ny)));
+        cells
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+use async_channel::{Receiver, Sender};
+use futures_timer::Delay;
+use super::mpu6050::Mpu6050ObserverData;
+use super::max7219::{Animation, Max7219Action};
+
+mod geometry;
+use geometry::{Chamber, HourglassGeometry, CHAMBER_SIZE};
+mod sand;
+use sand::SandGrid;
+mod timer;
+use timer::GrainTimer;
+mod flip;
+use flip::FlipDetector;
+pub use flip::FlipMode;
+mod animations;
+
+
+const GRAIN_COUNT: usize = 32;
+// MAX7219 chain positions of the chamber displays
+pub const UPPER_DISPLAY: usize = 0;
+pub const LOWER_DISPLAY: usize = 1;
+const STEP_PERIOD: Duration = Duration::from_millis(50);
+
+
+pub struct LogicConfig {
+    // time in which all grains pass from the top to the bottom chamber, e.g. 1, 3, 5 or 15 minutes
+    pub duration: Duration,
+    pub flip_mode: FlipMode,
+}
+
+
+pub struct Logic {
+    acc_observer: Receiver<Mpu6050ObserverData>,
+    led_matrix_server: Sender<Max7219Action>,
+
+    grid: SandGrid,
+    timer: GrainTimer,
+    flip_detector: FlipDetector,
+    flip_mode: FlipMode,
+    top: Chamber,
+    finished: bool,
+    gravity: (f32, f32),
+    led_rows: [[u8; CHAMBER_SIZE as usize]; 2],
+}
+
+pub async fn logic_task(acc_observer: Receiver<Mpu6050ObserverData>, led_matrix_server: Sender<Max7219Action>, config: LogicConfig)
+{
+    let mut grid = SandGrid::new(HourglassGeometry);
+    grid.fill(Chamber::Upper, GRAIN_COUNT);
+
+    let mut this = Logic {
+        acc_observer,
+        led_matrix_server,
+        grid,
+        timer: GrainTimer::new(config.duration, GRAIN_COUNT, Instant::now()),
+        flip_detector: FlipDetector::new(Chamber::Upper),
+        flip_mode: config.flip_mode,
+        top: Chamber::Upper,
+        finished: false,
+        gravity: (0f32, 0f32),
+        led_rows: [[0; CHAMBER_SIZE as usize]; 2],
+    };
+
+    this.run().await;
+}
+
+impl Logic {
+
+    // send frames of the chambers which changed since the last update
+    async fn update_led_matrix(&mut self) {
+        for (idx, (chamber, device)) in [(Chamber::Upper, UPPER_DISPLAY), (Chamber::Lower, LOWER_DISPLAY)].into_iter().enumerate() {
+            let rows = self.grid.chamber_rows(chamber);
+            if rows != self.led_rows[idx] {
+                self.led_matrix_server.send(Max7219Action::SetFrame { device, rows }).await.unwrap();
+                self.led_rows[idx] = rows;
+            }
+        }
+    }
+
+    async fn clear_led_matrix(&mut self) {
+        self.led_matrix_server.send(Max7219Action::ClearScreen).await.unwrap();
+        self.led_rows = [[0; CHAMBER_SIZE as usize]; 2];
+    }
+
+    fn handle_logic_acc_vec(&mut self, acc_vec: (f32, f32, f32)) {
+        // sensor y axis points along the matrix columns and x axis against the matrix rows
+        self.gravity = (acc_vec.1, -acc_vec.0);
+    }
+
+    // plays the animation and waits until its first pass is over
+    async fn play_animation(&mut self, animation: Animation) {
+        let duration = animation.duration();
+        self.led_matrix_server.send(Max7219Action::PlayAnimation(animation)).await.unwrap();
+        Delay::new(duration).await;
+    }
+
+    async fn handle_flip(&mut self, now: Instant) {
+        // hourglass axis is the diagonal from the upper to the lower chamber
+        let axis = (self.gravity.0 + self.gravity.1) / std::f32::consts::SQRT_2;
+
+        let Some(top) = self.flip_detector.update(axis, now) else {
+            return;
+        };
+
+        self.top = top;
+        if self.finished {
+            // stop the time up animation and show the sand again
+            self.finished = false;
+            self.clear_led_matrix().await;
+            self.update_led_matrix().await;
+        }
+
+        match self.flip_mode {
+            FlipMode::Continue => {
+                let released = GRAIN_COUNT - self.grid.chamber_grain_count(top);
+                self.timer.resume(now, released);
+            }
+            FlipMode::Restart => {
+                self.grid.fill(top, GRAIN_COUNT);
+                self.timer.restart(now);
+            }
+        }
+
+        log::info!("Logic: flipped, {:?} chamber on top, {} s left", top, self.timer.remaining(now).as_secs());
+    }
+
+    pub async fn run(&mut self) {
+        log::info!("Logic started");
+
+        self.play_animation(animations::boot_splash()).await;
+        self.clear_led_matrix().await;
+        self.update_led_matrix().await;
+        self.timer.restart(Instant::now());
+        let mut next_step = Instant::now();
+
+        loop {
+            // keep only the latest sensor reading, the simulation runs even when no new data arrives
+            while let Ok(acc_data) = self.acc_observer.try_recv() {
+                // filtered gravity stays stable while the hourglass is being flipped
+                self.handle_logic_acc_vec(acc_data.gravity);
+            }
+
+            let current_time = Instant::now();
+
+            if current_time >= next_step {
+                next_step = current_time + STEP_PERIOD;
+
+                self.handle_flip(current_time).await;
+
+                let result = self.grid.step(self.gravity, self.top, self.timer.budget(current_time));
+                self.timer.released(result.passed);
+                // the time up animation stays on the display until the hourglass gets flipped
+                if !self.finished && result.moved {
+                    self.update_led_matrix().await;
+                }
+
+                if !self.finished && self.timer.is_finished(current_time) {
+                    self.finished = true;
+                    log::info!("Logic: time is up");
+                    self.led_matrix_server.send(Max7219Action::PlayAnimation(animations::time_up())).await.unwrap();
+                }
+            }
+
+            Delay::new(Duration::from_millis(10)).await;
+        }
+
+    }
+
+}
@@ -0,0 +1,233 @@
+use super::geometry::{Chamber, HourglassGeometry, CHAMBER_SIZE};
+
+// minimal length of the gravity vector (in g) needed to move the grains
+const MIN_GRAVITY: f32 = 0.2f32;
+// tan(22.5deg), below this ratio the smaller gravity component is ignored
+const DIRECTION_RATIO: f32 = 0.414f32;
+
+
+pub struct StepResult {
+    // any grain changed its position
+    pub moved: bool,
+    // number of grains which passed through the neck
+    pub passed: usize,
+}
+
+
+// Cellular automaton sand simulation. Every grain falls one cell per step along the
+// gravity direction, slides diagonally when blocked and otherwise stays where it is.
+// Cells outside of the hourglass chambers are walls.
+pub struct SandGrid {
+    geometry: HourglassGeometry,
+    width: u8,
+    height: u8,
+    cells: Vec<bool>,
+    seed: u32,
+}
+
+impl SandGrid {
+
+    pub fn new(geometry: HourglassGeometry) -> Self {
+        let width = geometry.width();
+        let height = geometry.height();
+        Self {
+            geometry,
+            width,
+            height,
+            cells: vec![false; width as usize * height as usize],
+            seed: 0x2545F491,
+        }
+    }
+
+    pub fn is_cell(&self, x: i32, y: i32) -> bool {
+        self.geometry.is_cell(x, y)
+    }
+
+    pub fn get(&self, x: u8, y: u8) -> bool {
+        self.is_cell(x as i32, y as i32) && self.cells[self.index(x as i32, y as i32)]
+    }
+
+    pub fn grain_count(&self) -> usize {
+        self.cells.iter().filter(|c| **c).count()
+    }
+
+    // remove all grains and put count grains into the chamber, starting from its far end
+    pub fn fill(&mut self, chamber: Chamber, count: usize) {
+        self.cells.iter_mut().for_each(|c| *c = false);
+        for (x, y) in self.geometry.cells_from_far_end(chamber).into_iter().take(count) {
+            let idx = self.index(x as i32, y as i32);
+            self.cells[idx] = true;
+        }
+    }
+
+    pub fn chamber_grain_count(&self, chamber: Chamber) -> usize {
+        self.chamber_rows(chamber).iter().map(|row| row.count_ones() as usize).sum()
+    }
+
+    // returns rows of the chamber in its local coordinates as bit masks,
+    // bit x set when cell (x, y) holds a grain
+    pub fn chamber_rows(&self, chamber: Chamber) -> [u8; CHAMBER_SIZE as usize] {
+        let (ox, oy) = self.geometry.origin(chamber);
+        let mut rows = [0u8; CHAMBER_SIZE as usize];
+        for (y, row) in rows.iter_mut().enumerate() {
+            for x in 0..CHAMBER_SIZE {
+                if self.get(ox + x, oy + y as u8) {
+                    *row |= 1 << x;
+                }
+            }
+        }
+        rows
+    }
+
+    // move all grains by one cell along the gravity vector, at most neck_budget grains
+    // may pass through the neck and only out of the top chamber
+    pub fn step(&mut self, gravity: (f32, f32), top: Chamber, neck_budget: usize) -> StepResult {
+        let mut result = StepResult { moved: false, passed: 0 };
+        let Some((dx, dy)) = gravity_direction(gravity) else {
+            return result;
+        };
+
+        // grains lying lowest along the gravity direction move first so the whole pile falls together
+        let mut grains: Vec<(i32, i32)> = Vec::new();
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                if self.cells[self.index(x, y)] {
+                    grains.push((x, y));
+                }
+            }
+        }
+        grains.sort_by_key(|(x, y)| -(x * dx + y * dy));
+
+        let count = grains.len();
+
+        for (x, y) in grains {
+            // both diagonal slides are tried in random order, so piles do not lean to one side
+            let (slide_a, slide_b) = rotate_45(dx, dy);
+            let (slide_a, slide_b) = if self.random_bit() { (slide_a, slide_b) } else { (slide_b, slide_a) };
+
+            for (mx, my) in [(dx, dy), slide_a, slide_b] {
+                let (nx, ny) = (x + mx, y + my);
+                if !self.is_cell(nx, ny) || self.cells[self.index(nx, ny)] {
+                    continue;
+                }
+
+                let from_chamber = self.geometry.chamber(x, y);
+                let passes_neck = from_chamber != self.geometry.chamber(nx, ny);
+                if passes_neck && (from_chamber != Some(top) || result.passed >= neck_budget) {
+                    continue;
+                }
+
+                let from = self.index(x, y);
+                let to = self.index(nx, ny);
+                self.cells[from] = false;
+                self.cells[to] = true;
+                result.moved = true;
+                if passes_neck {
+                    result.passed += 1;
+                }
+                break;
+            }
+        }
+
+        debug_assert_eq!(count, self.grain_count());
+
+        result
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    // xorshift32, good enough to break the symmetry of diagonal slides
+    fn random_bit(&mut self) -> bool {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed & 1 == 1
+    }
+}
+
+
+// quantize gravity vector to one of 8 grid directions
+fn gravity_direction(gravity: (f32, f32)) -> Option<(i32, i32)> {
+    let (gx, gy) = gravity;
+    if (gx * gx + gy * gy).sqrt() < MIN_GRAVITY {
+        return None;
+    }
+
+    let dx = if gx.abs() < gy.abs() * DIRECTION_RATIO { 0 } else { gx.signum() as i32 };
+    let dy = if gy.abs() < gx.abs() * DIRECTION_RATIO { 0 } else { gy.signum() as i32 };
+
+    Some((dx, dy))
+}
+
+// the two directions 45deg to both sides of (dx, dy)
+fn rotate_45(dx: i32, dy: i32) -> ((i32, i32), (i32, i32)) {
+    (((dx - dy).signum(), (dx + dy).signum()), ((dx + dy).signum(), (dy - dx).signum()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWN: (f32, f32) = (1f32, 1f32);
+
+    #[test]
+    fn grains_are_conserved() {
+        let mut grid = SandGrid::new(HourglassGeometry);
+        grid.fill(Chamber::Upper, 40);
+        for gravity in [DOWN, (1f32, 0f32), (-1f32, 0.3f32), (0f32, -1f32), (-1f32, -1f32)] {
+            for _ in 0..50 {
+                grid.step(gravity, Chamber::Upper, 1);
+                assert_eq!(grid.grain_count(), 40);
+            }
+        }
+    }
+
+    #[test]
+    fn grains_cross_only_through_the_neck() {
+        let mut grid = SandGrid::new(HourglassGeometry);
+        grid.fill(Chamber::Upper, 64);
+        // sideways gravity presses grains against the whole edge shared with the lower chamber
+        for gravity in [(1f32, 0f32), (0f32, 1f32), DOWN] {
+            for _ in 0..30 {
+                let lower = grid.chamber_grain_count(Chamber::Lower);
+                let result = grid.step(gravity, Chamber::Upper, 1);
+                assert_eq!(grid.chamber_grain_count(Chamber::Lower), lower + result.passed);
+                if result.passed > 0 {
+                    assert!(grid.get(8, 8));
+                }
+            }
+        }
+        assert!(grid.chamber_grain_count(Chamber::Lower) > 0);
+    }
+
+    #[test]
+    fn neck_budget_is_respected() {
+        let mut grid = SandGrid::new(HourglassGeometry);
+        grid.fill(Chamber::Upper, 64);
+        for _ in 0..30 {
+            assert_eq!(grid.step(DOWN, Chamber::Upper, 0).passed, 0);
+        }
+        assert_eq!(grid.chamber_grain_count(Chamber::Lower), 0);
+
+        let mut passed = 0;
+        for _ in 0..30 {
+            let result = grid.step(DOWN, Chamber::Upper, 1);
+            assert!(result.passed <= 1);
+            passed += result.passed;
+        }
+        assert_eq!(grid.chamber_grain_count(Chamber::Lower), passed);
+    }
+
+    #[test]
+    fn grains_do_not_leave_the_bottom_chamber() {
+        let mut grid = SandGrid::new(HourglassGeometry);
+        grid.fill(Chamber::Lower, 64);
+        for _ in 0..30 {
+            assert_eq!(grid.step((-1f32, -1f32), Chamber::Upper, 10).passed, 0);
+        }
+        assert_eq!(grid.chamber_grain_count(Chamber::Upper), 0);
+    }
+}
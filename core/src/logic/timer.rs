@@ -0,0 +1,117 @@
+use std::time::{Duration, Instant};
+
+
+// Countdown which lets grains through the neck at a constant rate, so the
+// top chamber gets empty exactly when the configured duration elapses.
+pub struct GrainTimer {
+    duration: Duration,
+    grains: usize,
+    start: Instant,
+    // time already elapsed when the countdown (re)started at start
+    offset: Duration,
+    released: usize,
+}
+
+impl GrainTimer {
+
+    pub fn new(duration: Duration, grains: usize, now: Instant) -> Self {
+        Self {
+            duration,
+            grains,
+            start: now,
+            offset: Duration::ZERO,
+            released: 0,
+        }
+    }
+
+    pub fn restart(&mut self, now: Instant) {
+        self.start = now;
+        self.offset = Duration::ZERO;
+        self.released = 0;
+    }
+
+    // continue the countdown as if released grains already passed the neck
+    pub fn resume(&mut self, now: Instant, released: usize) {
+        let released = released.min(self.grains);
+        self.start = now;
+        self.offset = if self.grains == 0 {
+            self.duration
+        } else {
+            self.duration.mul_f64(released as f64 / self.grains as f64)
+        };
+        self.released = released;
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        (self.offset + now.saturating_duration_since(self.start)).min(self.duration)
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.duration - self.elapsed(now)
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.released >= self.grains && self.remaining(now).is_zero()
+    }
+
+    // number of grains which should have passed the neck until now
+    pub fn due(&self, now: Instant) -> usize {
+        if self.duration.is_zero() {
+            return self.grains;
+        }
+        let due = self.elapsed(now).as_nanos() * self.grains as u128 / self.duration.as_nanos();
+        (due as usize).min(self.grains)
+    }
+
+    // number of grains allowed to pass the neck right now
+    pub fn budget(&self, now: Instant) -> usize {
+        self.due(now).saturating_sub(self.released)
+    }
+
+    pub fn released(&mut self, count: usize) {
+        self.released += count;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grains_are_due_at_a_constant_rate() {
+        let start = Instant::now();
+        let timer = GrainTimer::new(Duration::from_secs(10), 100, start);
+        assert_eq!(timer.due(start), 0);
+        assert_eq!(timer.due(start + Duration::from_secs(1)), 10);
+        assert_eq!(timer.due(start + Duration::from_secs(10)), 100);
+        assert_eq!(timer.due(start + Duration::from_secs(20)), 100);
+    }
+
+    #[test]
+    fn budget_excludes_released_grains() {
+        let start = Instant::now();
+        let mut timer = GrainTimer::new(Duration::from_secs(10), 100, start);
+        let now = start + Duration::from_secs(1);
+        timer.released(4);
+        assert_eq!(timer.budget(now), 6);
+        timer.released(6);
+        assert_eq!(timer.budget(now), 0);
+        assert!(!timer.is_finished(now));
+    }
+
+    #[test]
+    fn resume_continues_from_released_grains() {
+        let start = Instant::now();
+        let mut timer = GrainTimer::new(Duration::from_secs(10), 100, start);
+        let now = start + Duration::from_secs(2);
+        timer.resume(now, 50);
+        assert_eq!(timer.due(now), 50);
+        assert_eq!(timer.budget(now), 0);
+        assert_eq!(timer.remaining(now), Duration::from_secs(5));
+        assert_eq!(timer.budget(now + Duration::from_secs(1)), 10);
+
+        timer.released(50);
+        assert!(timer.is_finished(now + Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,158 @@
+use std::time::Duration;
+use super::grayscale::GrayFrame;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayMode {
+    // play all frames once and keep the last one shown
+    Once,
+    // start again from the first frame after the last one
+    Loop,
+    // play frames forward and backward
+    PingPong,
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnimationFrame {
+    // framebuffer of every device, devices[i] goes to device i, missing devices keep their content
+    pub devices: Vec<[u8; 8]>,
+    // gray levels of every device, shown instead of the framebuffers in grayscale mode
+    pub gray: Vec<GrayFrame>,
+    pub duration: Duration,
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode,
+}
+
+impl Animation {
+
+    pub fn new(mode: PlayMode) -> Self {
+        Self { frames: Vec::new(), mode }
+    }
+
+    pub fn frame(mut self, devices: Vec<[u8; 8]>, duration: Duration) -> Self {
+        self.frames.push(AnimationFrame { devices, gray: Vec::new(), duration });
+        self
+    }
+
+    pub fn gray_frame(mut self, gray: Vec<GrayFrame>, duration: Duration) -> Self {
+        self.frames.push(AnimationFrame { devices: Vec::new(), gray, duration });
+        self
+    }
+
+    // length of one pass through all frames
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+
+// Playback position within an animation.
+pub struct AnimationPlayer {
+    animation: Animation,
+    index: usize,
+    forward: bool,
+}
+
+impl AnimationPlayer {
+
+    pub fn new(animation: Animation) -> Self {
+        Self { animation, index: 0, forward: true }
+    }
+
+    pub fn current(&self) -> Option<&AnimationFrame> {
+        self.animation.frames.get(self.index)
+    }
+
+    // move to the next frame, returns false when the animation is finished
+    pub fn advance(&mut self) -> bool {
+        let count = self.animation.frames.len();
+        if count == 0 {
+            return false;
+        }
+
+        match self.animation.mode {
+            PlayMode::Once => {
+                self.index += 1;
+                self.index < count
+            }
+            PlayMode::Loop => {
+                self.index = (self.index + 1) % count;
+                true
+            }
+            PlayMode::PingPong => {
+                if count > 1 {
+                    if self.forward && self.index + 1 == count {
+                        self.forward = false;
+                    } else if !self.forward && self.index == 0 {
+                        self.forward = true;
+                    }
+                    self.index = if self.forward { self.index + 1 } else { self.index - 1 };
+                }
+                true
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // frames are told apart by their duration in milliseconds, None once the player finished
+    fn sequence(mode: PlayMode, frames: u64, steps: usize) -> Vec<Option<u64>> {
+        let animation = (0..frames).fold(Animation::new(mode), |animation, ms| {
+            animation.frame(Vec::new(), Duration::from_millis(ms))
+        });
+        let mut player = AnimationPlayer::new(animation);
+        let mut shown = vec![player.current().map(|frame| frame.duration.as_millis() as u64)];
+        for _ in 0..steps {
+            let playing = player.advance();
+            shown.push(player.current().filter(|_| playing).map(|frame| frame.duration.as_millis() as u64));
+        }
+        shown
+    }
+
+    #[test]
+    fn once_stops_after_the_last_frame() {
+        assert_eq!(sequence(PlayMode::Once, 3, 3), vec![Some(0), Some(1), Some(2), None]);
+        assert_eq!(sequence(PlayMode::Once, 1, 1), vec![Some(0), None]);
+    }
+
+    #[test]
+    fn loop_starts_again_from_the_first_frame() {
+        assert_eq!(sequence(PlayMode::Loop, 3, 5), vec![Some(0), Some(1), Some(2), Some(0), Some(1), Some(2)]);
+        assert_eq!(sequence(PlayMode::Loop, 1, 2), vec![Some(0), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn ping_pong_shows_end_frames_once() {
+        assert_eq!(
+            sequence(PlayMode::PingPong, 3, 6),
+            vec![Some(0), Some(1), Some(2), Some(1), Some(0), Some(1), Some(2)],
+        );
+        assert_eq!(sequence(PlayMode::PingPong, 2, 3), vec![Some(0), Some(1), Some(0), Some(1)]);
+        assert_eq!(sequence(PlayMode::PingPong, 1, 2), vec![Some(0), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn empty_animation_finishes_immediately() {
+        for mode in [PlayMode::Once, PlayMode::Loop, PlayMode::PingPong] {
+            assert_eq!(sequence(mode, 0, 1), vec![None, None]);
+        }
+    }
+
+    #[test]
+    fn duration_of_one_pass() {
+        let animation = Animation::new(PlayMode::Loop)
+            .frame(Vec::new(), Duration::from_millis(30))
+            .gray_frame(Vec::new(), Duration::from_millis(70));
+        assert_eq!(animation.duration(), Duration::from_millis(100));
+    }
+}
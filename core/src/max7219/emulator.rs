@@ -0,0 +1,408 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::convert::Infallible;
+use crate::spi::SpiTransportInterface;
+use super::register::Register;
+
+
+// Register state of one emulated MAX7219 chip.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Max7219ChipState {
+    pub digits: [u8; 8],
+    pub decode_mode: u8,
+    pub intensity: u8,
+    pub scan_limit: u8,
+    pub shutdown: bool,
+    pub display_test: bool,
+}
+
+impl Default for Max7219ChipState {
+    // state after power up according to the datasheet
+    fn default() -> Self {
+        Self {
+            digits: [0; 8],
+            decode_mode: 0,
+            intensity: 0,
+            scan_limit: 0,
+            shutdown: true,
+            display_test: false,
+        }
+    }
+}
+
+impl Max7219ChipState {
+
+    // rows of leds which are lit, taking test mode, shutdown and scan limit into account
+    pub fn visible_rows(&self) -> [u8; 8] {
+        if self.display_test {
+            return [0xFF; 8];
+        }
+
+        let mut rows = [0u8; 8];
+        if !self.shutdown {
+            for (row, digit) in rows.iter_mut().zip(self.digits.iter()).take(self.scan_limit as usize + 1) {
+                *row = *digit;
+            }
+        }
+        rows
+    }
+
+    fn latch(&mut self, register: u8, data: u8) {
+        match register {
+            r if r == Register::NoOp.addr() => {}
+            r if (Register::Digit0.addr()..=Register::Digit7.addr()).contains(&r) => {
+                self.digits[(r - Register::Digit0.addr()) as usize] = data;
+            }
+            r if r == Register::DecodeMode.addr() => self.decode_mode = data,
+            r if r == Register::Intensity.addr() => self.intensity = data & 0x0F,
+            r if r == Register::ScanLimit.addr() => self.scan_limit = data & 0x07,
+            r if r == Register::Shutdown.addr() => self.shutdown = data & 0x01 == 0,
+            r if r == Register::DisplayTest.addr() => self.display_test = data & 0x01 == 1,
+            _ => {}
+        }
+    }
+}
+
+
+struct EmulatorState {
+    chips: Vec<Max7219ChipState>,
+    // contents of the chained 16 bit shift registers, the last byte is in device 0
+    shift: VecDeque<u8>,
+    writes: usize,
+}
+
+
+// SPI transport emulating a chain of MAX7219 chips in memory. Clones share the state,
+// so a test can keep one to inspect what the display shows while the driver owns another.
+#[derive(Clone)]
+pub struct Max7219Emulator {
+    state: Arc<Mutex<EmulatorState>>,
+}
+
+impl Max7219Emulator {
+
+    pub fn new(devices: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(EmulatorState {
+                chips: vec![Max7219ChipState::default(); devices],
+                shift: VecDeque::from(vec![0u8; 2 * devices]),
+                writes: 0,
+            })),
+        }
+    }
+
+    pub fn chip(&self, device: usize) -> Max7219ChipState {
+        self.state.lock().unwrap().chips[device]
+    }
+
+    pub fn chips(&self) -> Vec<Max7219ChipState> {
+        self.state.lock().unwrap().chips.clone()
+    }
+
+    // number of SPI frames received
+    pub fn writes(&self) -> usize {
+        self.state.lock().unwrap().writes
+    }
+}
+
+impl SpiTransportInterface for Max7219Emulator {
+    type Error = Infallible;
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), Infallible> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        // bytes shift through the whole chain, older ones drop out of the last device
+        for byte in data {
+            state.shift.pop_front();
+            state.shift.push_back(*byte);
+        }
+
+        // chip select goes high, every device latches the register/data pair in its shift register
+        let len = state.shift.len();
+        for (device, chip) in state.chips.iter_mut().enumerate() {
+            let register = state.shift[len - 2 * device - 2];
+            let data = state.shift[len - 2 * device - 1];
+            chip.latch(register, data);
+        }
+        state.writes += 1;
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use std::time::{Duration, Instant};
+    use futures::executor::block_on;
+    use futures::future::select;
+    use futures_timer::Delay;
+    use super::*;
+    use super::super::{Max7219, Max7219Action, Max7219Config, GrayFrame, Animation, PlayMode};
+
+    const FRAME: [u8; 8] = [0, 0, 0x3C, 0, 0, 0x81, 0, 0];
+
+    fn init(emulator: &mut Max7219Emulator) -> Max7219<'_, Max7219Emulator, 2> {
+        let mut driver = Max7219::new(emulator, None, Max7219Config::default());
+        block_on(driver.init()).unwrap();
+        driver
+    }
+
+    #[test]
+    fn init_configures_all_devices() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        init(&mut emulator);
+
+        for chip in display.chips() {
+            assert!(!chip.shutdown);
+            assert!(!chip.display_test);
+            assert_eq!(chip.decode_mode, 0);
+            assert_eq!(chip.scan_limit, 7);
+            assert_eq!(chip.intensity, 0);
+            assert_eq!(chip.visible_rows(), [0; 8]);
+        }
+    }
+
+    #[test]
+    fn set_frame_writes_dirty_rows_only() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::SetFrame { device: 0, rows: [0xFF; 8] }).await.unwrap();
+            driver.flush().await.unwrap();
+
+            let writes = display.writes();
+            driver.handle_action(Max7219Action::SetFrame { device: 1, rows: FRAME }).await.unwrap();
+            driver.flush().await.unwrap();
+            // only rows 2 and 5 changed, device 0 got a no-op in both frames
+            assert_eq!(display.writes(), writes + 2);
+
+            // nothing changed, nothing is sent
+            driver.handle_action(Max7219Action::SetFrame { device: 1, rows: FRAME }).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.writes(), writes + 2);
+        });
+
+        assert_eq!(display.chip(0).digits, [0xFF; 8]);
+        assert_eq!(display.chip(1).digits, FRAME);
+        assert_eq!(display.chip(1).visible_rows(), FRAME);
+    }
+
+    #[test]
+    fn intensity_and_power() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::SetFrame { device: 0, rows: FRAME }).await.unwrap();
+            driver.flush().await.unwrap();
+            driver.handle_action(Max7219Action::SetIntensity { device: 1, intensity: 9 }).await.unwrap();
+            driver.handle_action(Max7219Action::SetPower { on: false }).await.unwrap();
+        });
+
+        assert_eq!(display.chip(0).intensity, 0);
+        assert_eq!(display.chip(1).intensity, 9);
+        assert!(display.chips().iter().all(|chip| chip.shutdown));
+        // shutdown blanks the leds but keeps the digit registers
+        assert_eq!(display.chip(0).visible_rows(), [0; 8]);
+        assert_eq!(display.chip(0).digits, FRAME);
+    }
+
+    // levels 0 - 3 in the first row, plane 0 lights x = 1, 3 and plane 1 lights x = 2, 3
+    fn gray_frame() -> GrayFrame {
+        let mut levels = [[0u8; 8]; 8];
+        levels[0][..4].copy_from_slice(&[0, 1, 2, 3]);
+        levels
+    }
+    const PLANE_0: [u8; 8] = [0b1010, 0, 0, 0, 0, 0, 0, 0];
+    const PLANE_1: [u8; 8] = [0b1100, 0, 0, 0, 0, 0, 0, 0];
+
+    #[test]
+    fn grayscale_shows_bit_planes() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::SetFrame { device: 0, rows: [0xFF; 8] }).await.unwrap();
+            driver.handle_action(Max7219Action::SetGrayFrame { device: 0, levels: gray_frame() }).await.unwrap();
+            driver.flush().await.unwrap();
+            // gray frames are kept but not shown until grayscale mode is on
+            assert_eq!(display.chip(0).digits, [0xFF; 8]);
+
+            driver.handle_action(Max7219Action::SetGrayscale { on: true }).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, PLANE_0);
+
+            // only the first row differs between the planes
+            let writes = display.writes();
+            driver.next_gray_plane();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, PLANE_1);
+            assert_eq!(display.writes(), writes + 1);
+
+            driver.next_gray_plane();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, PLANE_0);
+
+            // clearing the screen clears the gray frames as well
+            driver.handle_action(Max7219Action::ClearScreen).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, [0; 8]);
+
+            driver.handle_action(Max7219Action::SetGrayscale { on: false }).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, [0; 8]);
+        });
+    }
+
+    #[test]
+    fn run_cycles_bit_planes() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let config = Max7219Config { gray_slot: Duration::from_millis(10), ..Max7219Config::default() };
+        let mut driver = Max7219::<_, 2>::new(&mut emulator, None, config);
+        block_on(driver.init()).unwrap();
+        driver.set_gray_frame(0, gray_frame());
+        driver.set_grayscale(true);
+
+        // sample what device 0 shows while the driver runs
+        let mut shown = Vec::new();
+        let sample = async {
+            let end = Instant::now() + Duration::from_millis(300);
+            while Instant::now() < end {
+                shown.push((Instant::now(), display.chip(0).digits));
+                Delay::new(Duration::from_millis(1)).await;
+            }
+        };
+        block_on(select(pin!(driver.run()), pin!(sample)));
+
+        assert!(shown.iter().all(|(_, rows)| *rows == PLANE_0 || *rows == PLANE_1));
+        // plane 1 is shown twice as long as plane 0, every sample lasts until the next one
+        let mut time = [Duration::ZERO; 2];
+        for pair in shown.windows(2) {
+            let plane = if pair[0].1 == PLANE_0 { 0 } else { 1 };
+            time[plane] += pair[1].0 - pair[0].0;
+        }
+        let ratio = time[1].as_secs_f32() / time[0].as_secs_f32();
+        assert!((1.4..2.8).contains(&ratio), "ratio {}", ratio);
+    }
+
+    fn blinking() -> Animation {
+        Animation::new(PlayMode::Loop)
+            .frame(vec![FRAME], Duration::from_millis(20))
+            .frame(vec![[0xFF; 8]], Duration::from_millis(20))
+    }
+
+    #[test]
+    fn stopped_animation_keeps_its_frame() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::PlayAnimation(blinking())).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, FRAME);
+            driver.animation_step();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, [0xFF; 8]);
+
+            driver.handle_action(Max7219Action::StopAnimation).await.unwrap();
+            assert_eq!(driver.animation_timeout(), None);
+            driver.animation_step();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, [0xFF; 8]);
+        });
+    }
+
+    #[test]
+    fn clear_screen_interrupts_animation() {
+        let mut emulator = Max7219Emulator::new(2);
+        let display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::PlayAnimation(blinking())).await.unwrap();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, FRAME);
+
+            driver.handle_action(Max7219Action::ClearScreen).await.unwrap();
+            assert_eq!(driver.animation_timeout(), None);
+            driver.animation_step();
+            driver.flush().await.unwrap();
+            assert_eq!(display.chip(0).digits, [0; 8]);
+        });
+    }
+
+    // device 0 enters test mode, device 1 shuts down, both lose their scan limit and row 2
+    fn corrupt(emulator: &mut Max7219Emulator) {
+        block_on(async {
+            emulator.write(&[0x0C, 0x00, 0x0F, 0x01]).await.unwrap();
+            emulator.write(&[0x0B, 0x02, 0x0B, 0x03]).await.unwrap();
+            emulator.write(&[0x03, 0x5A, 0x03, 0xA5]).await.unwrap();
+        });
+    }
+
+    fn assert_restored(display: &Max7219Emulator) {
+        for (device, chip) in display.chips().iter().enumerate() {
+            assert!(!chip.shutdown && !chip.display_test, "device {}", device);
+            assert_eq!(chip.scan_limit, 7);
+            assert_eq!(chip.decode_mode, 0);
+        }
+        assert_eq!(display.chip(0).visible_rows(), FRAME);
+        assert_eq!(display.chip(1).visible_rows(), [0xFF; 8]);
+        assert_eq!(display.chip(1).intensity, 9);
+    }
+
+    #[test]
+    fn refresh_restores_corrupted_devices() {
+        let mut emulator = Max7219Emulator::new(2);
+        let mut display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::SetFrame { device: 0, rows: FRAME }).await.unwrap();
+            driver.handle_action(Max7219Action::SetFrame { device: 1, rows: [0xFF; 8] }).await.unwrap();
+            driver.handle_action(Max7219Action::SetIntensity { device: 1, intensity: 9 }).await.unwrap();
+            driver.flush().await.unwrap();
+        });
+        assert_restored(&display);
+
+        corrupt(&mut display);
+        assert_eq!(display.chip(0).visible_rows(), [0xFF; 8]);
+        assert_eq!(display.chip(1).visible_rows(), [0; 8]);
+
+        block_on(driver.refresh()).unwrap();
+        assert_restored(&display);
+    }
+
+    #[test]
+    fn reinit_restores_corrupted_devices() {
+        let mut emulator = Max7219Emulator::new(2);
+        let mut display = emulator.clone();
+        let mut driver = init(&mut emulator);
+
+        block_on(async {
+            driver.handle_action(Max7219Action::SetFrame { device: 0, rows: FRAME }).await.unwrap();
+            driver.handle_action(Max7219Action::SetFrame { device: 1, rows: [0xFF; 8] }).await.unwrap();
+            driver.handle_action(Max7219Action::SetIntensity { device: 1, intensity: 9 }).await.unwrap();
+            driver.flush().await.unwrap();
+        });
+
+        corrupt(&mut display);
+        block_on(async {
+            // init clears the devices, the framebuffers are sent again by the next flush
+            driver.handle_action(Max7219Action::Reinit).await.unwrap();
+            assert_eq!(display.chip(0).visible_rows(), [0; 8]);
+            driver.flush().await.unwrap();
+        });
+        assert_restored(&display);
+    }
+}
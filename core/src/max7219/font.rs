@@ -0,0 +1,161 @@
+// Bitmap fonts for the led matrices. Glyphs are stored as columns from left to right,
+// bit y of a column is the pixel in row y (bit 0 at the top).
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Font {
+    Font5x7,
+    Font3x5,
+}
+
+impl Font {
+
+    pub fn height(self) -> u8 {
+        match self {
+            Font::Font5x7 => 7,
+            Font::Font3x5 => 5,
+        }
+    }
+
+    // columns of the character, lower case letters are shown as upper case and
+    // characters missing in the font as '?'
+    pub fn glyph(self, c: char) -> &'static [u8] {
+        let c = c.to_ascii_uppercase();
+        match self {
+            Font::Font5x7 => find_glyph(&FONT_5X7, c),
+            Font::Font3x5 => find_glyph(&FONT_3X5, c),
+        }
+    }
+}
+
+
+fn find_glyph<const W: usize>(font: &'static [(char, [u8; W])], c: char) -> &'static [u8] {
+    font.iter()
+        .find(|(glyph_char, _)| *glyph_char == c)
+        .or_else(|| font.iter().find(|(glyph_char, _)| *glyph_char == '?'))
+        .map(|(_, columns)| columns.as_slice())
+        .unwrap_or(&[])
+}
+
+
+const FONT_5X7: [(char, [u8; 5]); 44] = [
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('!', [0x00, 0x00, 0x5F, 0x00, 0x00]),
+    ('%', [0x23, 0x13, 0x08, 0x64, 0x62]),
+    ('-', [0x08, 0x08, 0x08, 0x08, 0x08]),
+    ('.', [0x00, 0x60, 0x60, 0x00, 0x00]),
+    ('/', [0x20, 0x10, 0x08, 0x04, 0x02]),
+    ('0', [0x3E, 0x51, 0x49, 0x45, 0x3E]),
+    ('1', [0x00, 0x42, 0x7F, 0x40, 0x00]),
+    ('2', [0x42, 0x61, 0x51, 0x49, 0x46]),
+    ('3', [0x21, 0x41, 0x45, 0x4B, 0x31]),
+    ('4', [0x18, 0x14, 0x12, 0x7F, 0x10]),
+    ('5', [0x27, 0x45, 0x45, 0x45, 0x39]),
+    ('6', [0x3C, 0x4A, 0x49, 0x49, 0x30]),
+    ('7', [0x01, 0x71, 0x09, 0x05, 0x03]),
+    ('8', [0x36, 0x49, 0x49, 0x49, 0x36]),
+    ('9', [0x06, 0x49, 0x49, 0x29, 0x1E]),
+    (':', [0x00, 0x36, 0x36, 0x00, 0x00]),
+    ('?', [0x02, 0x01, 0x51, 0x09, 0x06]),
+    ('A', [0x7E, 0x11, 0x11, 0x11, 0x7E]),
+    ('B', [0x7F, 0x49, 0x49, 0x49, 0x36]),
+    ('C', [0x3E, 0x41, 0x41, 0x41, 0x22]),
+    ('D', [0x7F, 0x41, 0x41, 0x22, 0x1C]),
+    ('E', [0x7F, 0x49, 0x49, 0x49, 0x41]),
+    ('F', [0x7F, 0x09, 0x09, 0x01, 0x01]),
+    ('G', [0x3E, 0x41, 0x41, 0x51, 0x32]),
+    ('H', [0x7F, 0x08, 0x08, 0x08, 0x7F]),
+    ('I', [0x00, 0x41, 0x7F, 0x41, 0x00]),
+    ('J', [0x20, 0x40, 0x41, 0x3F, 0x01]),
+    ('K', [0x7F, 0x08, 0x14, 0x22, 0x41]),
+    ('L', [0x7F, 0x40, 0x40, 0x40, 0x40]),
+    ('M', [0x7F, 0x02, 0x04, 0x02, 0x7F]),
+    ('N', [0x7F, 0x04, 0x08, 0x10, 0x7F]),
+    ('O', [0x3E, 0x41, 0x41, 0x41, 0x3E]),
+    ('P', [0x7F, 0x09, 0x09, 0x09, 0x06]),
+    ('Q', [0x3E, 0x41, 0x51, 0x21, 0x5E]),
+    ('R', [0x7F, 0x09, 0x19, 0x29, 0x46]),
+    ('S', [0x46, 0x49, 0x49, 0x49, 0x31]),
+    ('T', [0x01, 0x01, 0x7F, 0x01, 0x01]),
+    ('U', [0x3F, 0x40, 0x40, 0x40, 0x3F]),
+    ('V', [0x1F, 0x20, 0x40, 0x20, 0x1F]),
+    ('W', [0x7F, 0x20, 0x18, 0x20, 0x7F]),
+    ('X', [0x63, 0x14, 0x08, 0x14, 0x63]),
+    ('Y', [0x03, 0x04, 0x78, 0x04, 0x03]),
+    ('Z', [0x61, 0x51, 0x49, 0x45, 0x43]),
+];
+
+const FONT_3X5: [(char, [u8; 3]); 44] = [
+    (' ', [0x00, 0x00, 0x00]),
+    ('!', [0x00, 0x17, 0x00]),
+    ('%', [0x19, 0x04, 0x13]),
+    ('-', [0x04, 0x04, 0x04]),
+    ('.', [0x00, 0x10, 0x00]),
+    ('/', [0x18, 0x04, 0x03]),
+    ('0', [0x1F, 0x11, 0x1F]),
+    ('1', [0x12, 0x1F, 0x10]),
+    ('2', [0x1D, 0x15, 0x17]),
+    ('3', [0x15, 0x15, 0x1F]),
+    ('4', [0x07, 0x04, 0x1F]),
+    ('5', [0x17, 0x15, 0x1D]),
+    ('6', [0x1F, 0x15, 0x1D]),
+    ('7', [0x01, 0x19, 0x07]),
+    ('8', [0x1F, 0x15, 0x1F]),
+    ('9', [0x17, 0x15, 0x1F]),
+    (':', [0x00, 0x0A, 0x00]),
+    ('?', [0x01, 0x15, 0x02]),
+    ('A', [0x1E, 0x05, 0x1E]),
+    ('B', [0x1F, 0x15, 0x0A]),
+    ('C', [0x0E, 0x11, 0x11]),
+    ('D', [0x1F, 0x11, 0x0E]),
+    ('E', [0x1F, 0x15, 0x11]),
+    ('F', [0x1F, 0x05, 0x01]),
+    ('G', [0x0E, 0x11, 0x1D]),
+    ('H', [0x1F, 0x04, 0x1F]),
+    ('I', [0x11, 0x1F, 0x11]),
+    ('J', [0x08, 0x10, 0x0F]),
+    ('K', [0x1F, 0x04, 0x1B]),
+    ('L', [0x1F, 0x10, 0x10]),
+    ('M', [0x1F, 0x06, 0x1F]),
+    ('N', [0x1F, 0x01, 0x1E]),
+    ('O', [0x0E, 0x11, 0x0E]),
+    ('P', [0x1F, 0x05, 0x02]),
+    ('Q', [0x0E, 0x19, 0x16]),
+    ('R', [0x1F, 0x05, 0x1A]),
+    ('S', [0x12, 0x15, 0x09]),
+    ('T', [0x01, 0x1F, 0x01]),
+    ('U', [0x1F, 0x10, 0x1F]),
+    ('V', [0x0F, 0x10, 0x0F]),
+    ('W', [0x1F, 0x0C, 0x1F]),
+    ('X', [0x1B, 0x04, 0x1B]),
+    ('Y', [0x03, 0x1C, 0x03]),
+    ('Z', [0x19, 0x15, 0x13]),
+];
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_fit_the_font_size() {
+        for (font, width) in [(Font::Font5x7, 5), (Font::Font3x5, 3)] {
+            for c in ('A'..='Z').chain('0'..='9') {
+                let glyph = font.glyph(c);
+                assert_eq!(glyph.len(), width, "{:?} {}", font, c);
+                assert!(glyph.iter().all(|column| column >> font.height() == 0), "{:?} {}", font, c);
+            }
+        }
+    }
+
+    #[test]
+    fn lower_case_is_shown_as_upper_case() {
+        assert_eq!(Font::Font5x7.glyph('a'), &[0x7E, 0x11, 0x11, 0x11, 0x7E]);
+        assert_eq!(Font::Font3x5.glyph('a'), Font::Font3x5.glyph('A'));
+    }
+
+    #[test]
+    fn missing_characters_are_shown_as_question_mark() {
+        assert_eq!(Font::Font5x7.glyph('~'), &[0x02, 0x01, 0x51, 0x09, 0x06]);
+        assert_eq!(Font::Font3x5.glyph('é'), Font::Font3x5.glyph('?'));
+    }
+}
@@ -0,0 +1,53 @@
+// Software grayscale for led matrices. Every led gets a level 0 - 3 which is split into
+// two bit planes, plane n is shown for 2^n time slots, so the led is lit for level slots
+// out of 3 and its perceived brightness follows the level.
+
+pub const GRAY_BITS: usize = 2;
+pub const MAX_GRAY_LEVEL: u8 = (1 << GRAY_BITS) - 1;
+
+// gray levels of the leds, levels[y][x] is the led (x, y)
+pub type GrayFrame = [[u8; 8]; 8];
+
+
+// rows of the bit plane, bit x of row y set when the level of led (x, y) has bit plane set
+pub fn bit_plane(frame: &GrayFrame, plane: usize) -> [u8; 8] {
+    let mut rows = [0u8; 8];
+    for (row, levels) in rows.iter_mut().zip(frame.iter()) {
+        for (x, level) in levels.iter().enumerate() {
+            if level.min(&MAX_GRAY_LEVEL) & (1 << plane) != 0 {
+                *row |= 1 << x;
+            }
+        }
+    }
+    rows
+}
+
+// number of time slots the bit plane is shown
+pub fn plane_slots(plane: usize) -> u32 {
+    1 << plane
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_are_split_into_bit_planes() {
+        let mut frame = [[0u8; 8]; 8];
+        frame[0] = [0, 1, 2, 3, 0, 0, 0, 9];
+        frame[5] = [3; 8];
+
+        // levels above the maximum are shown as the maximum
+        assert_eq!(bit_plane(&frame, 0), [0b1000_1010, 0, 0, 0, 0, 0xFF, 0, 0]);
+        assert_eq!(bit_plane(&frame, 1), [0b1000_1100, 0, 0, 0, 0, 0xFF, 0, 0]);
+    }
+
+    #[test]
+    fn planes_are_weighted_by_their_bit() {
+        assert_eq!(plane_slots(0), 1);
+        assert_eq!(plane_slots(1), 2);
+        // one cycle through all planes lasts MAX_GRAY_LEVEL slots
+        assert_eq!((0..GRAY_BITS).map(plane_slots).sum::<u32>(), MAX_GRAY_LEVEL as u32);
+    }
+}
@@ -0,0 +1,532 @@
+use std::time::{Duration, Instant};
+use std::pin::pin;
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use crate::spi::SpiTransportInterface;
+use async_channel::Receiver;
+
+mod register;
+use register::{cascade_frame, Command};
+mod transform;
+pub use transform::{Rotation, Transform};
+mod grayscale;
+pub use grayscale::GrayFrame;
+use grayscale::{bit_plane, plane_slots, GRAY_BITS};
+mod font;
+pub use font::Font;
+mod text;
+pub use text::text_frames;
+use text::scroll_animation;
+mod animation;
+pub use animation::{Animation, PlayMode};
+use animation::AnimationPlayer;
+#[cfg(any(test, feature = "emulator"))]
+mod emulator;
+#[cfg(any(test, feature = "emulator"))]
+pub use emulator::{Max7219ChipState, Max7219Emulator};
+
+
+pub enum Max7219Action {
+    ClearScreen,
+    SetLedState { device: usize, x: u8, y: u8, on: bool },
+    // replace all rows of the device, bit x of rows[y] is the led (x, y)
+    SetFrame { device: usize, rows: [u8; 8] },
+    // led brightness 0 - 15 of the device
+    SetIntensity { device: usize, intensity: u8 },
+    // shutdown mode blanks all devices keeping their registers
+    SetPower { on: bool },
+    // display test mode turns on all leds of all devices
+    SetDisplayTest { on: bool },
+    // change how the framebuffer of the device is mapped to its leds
+    SetTransform { device: usize, transform: Transform },
+    // initialize all devices again, e.g. after they lost configuration due to a supply glitch
+    Reinit,
+    // replace gray levels (0 - 3) of all leds of the device, shown in grayscale mode
+    SetGrayFrame { device: usize, levels: GrayFrame },
+    // in grayscale mode the gray frames are shown instead of the on/off framebuffer
+    SetGrayscale { on: bool },
+    // scroll text from right to left across all devices, moving by one column every period
+    ScrollText { text: String, font: Font, period: Duration },
+    // play animation replacing the one currently played
+    PlayAnimation(Animation),
+    // stop the animation, its current frame stays shown
+    StopAnimation,
+}
+
+
+// how often control registers and framebuffer are written again, MAX7219 chips can
+// go blank or into test mode after supply glitches or EMI and stay so until rewritten
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RefreshPolicy {
+    Never,
+    Every(Duration),
+}
+
+
+pub struct Max7219Config<const N: usize> {
+    // orientation of every device in the chain
+    pub transforms: [Transform; N],
+    pub refresh: RefreshPolicy,
+    // time slot of the least significant grayscale bit plane. One gray cycle takes 3 slots, so
+    // the 10 ms default repeats the gray levels at only ~33 Hz, which visibly flickers. Slots
+    // can't be shorter than the FreeRTOS tick (10 ms by default, see CONFIG_FREERTOS_HZ),
+    // smooth grayscale needs a faster tick and a shorter slot.
+    pub gray_slot: Duration,
+}
+
+impl<const N: usize> Default for Max7219Config<N> {
+    fn default() -> Self {
+        Self {
+            transforms: [Transform::default(); N],
+            refresh: RefreshPolicy::Never,
+            gray_slot: Duration::from_millis(10),
+        }
+    }
+}
+
+
+// Driver of N cascaded MAX7219 chips. Device 0 is the first chip in the chain (connected
+// to MOSI), so its register/data pair is the last one shifted out in every frame.
+pub struct Max7219<'a, T: SpiTransportInterface, const N: usize> {
+    spi: &'a mut T,
+    led_states: [[u8;8]; N],
+    // rows last written to the digit registers of every device
+    shown_rows: [[u8;8]; N],
+    transforms: [Transform; N],
+    intensity: [u8; N],
+    power_on: bool,
+    display_test: bool,
+    update: bool,
+    refresh: RefreshPolicy,
+    last_refresh: Instant,
+    gray_states: [GrayFrame; N],
+    grayscale: bool,
+    gray_slot: Duration,
+    gray_plane: usize,
+    gray_plane_end: Instant,
+    animation: Option<AnimationPlayer>,
+    animation_frame_end: Instant,
+    client: Option<Receiver<Max7219Action>>,
+    frame: Vec<u8>,
+}
+
+pub async fn max7219_task<T, const N: usize>(mut spi: T, client: Option<Receiver<Max7219Action>>, config: Max7219Config<N>) 
+where
+    T: SpiTransportInterface
+{
+    let mut this = Max7219::new(&mut spi, client, config);
+
+    this.init().await.unwrap();
+    //this.run_demo().await;
+    this.run().await;
+}
+
+impl<'a, T: SpiTransportInterface, const N: usize> Max7219<'a, T, N> {
+
+    pub fn new(spi: &'a mut T, client: Option<Receiver<Max7219Action>>, config: Max7219Config<N>) -> Self {
+        Self { spi,
+            led_states: [[0;8]; N],
+            shown_rows: [[0;8]; N],
+            transforms: config.transforms,
+            intensity: [0; N],
+            power_on: true,
+            display_test: false,
+            update: true,
+            refresh: config.refresh,
+            last_refresh: Instant::now(),
+            gray_states: [[[0; 8]; 8]; N],
+            grayscale: false,
+            gray_slot: config.gray_slot,
+            gray_plane: 0,
+            gray_plane_end: Instant::now(),
+            animation: None,
+            animation_frame_end: Instant::now(),
+            client,
+            frame: Vec::with_capacity(2 * N),
+        }
+    }
+
+    pub fn set_led(&mut self, device: usize, x: u8, y: u8, on: bool) {
+        if device >= N || x >= 8 || y >= 8 {
+            return;
+        } 
+
+        let row = &mut self.led_states[device][y as usize];
+        let x = 1 << x;
+
+        if on && ((*row & x) == 0) {
+            *row |= x;
+            self.update = true;
+        } else if !on && ((*row & x) == x) {
+            *row &= !x;
+            self.update = true;
+        }
+    }
+
+    pub fn set_frame(&mut self, device: usize, rows: [u8; 8]) {
+        if device >= N {
+            return;
+        }
+
+        if self.led_states[device] != rows {
+            self.led_states[device] = rows;
+            self.update = true;
+        }
+    }
+
+    pub fn set_transform(&mut self, device: usize, transform: Transform) {
+        if device < N && self.transforms[device] != transform {
+            self.transforms[device] = transform;
+            self.update = true;
+        }
+    }
+
+    pub fn set_gray_frame(&mut self, device: usize, levels: GrayFrame) {
+        if device < N && self.gray_states[device] != levels {
+            self.gray_states[device] = levels;
+            self.update |= self.grayscale;
+        }
+    }
+
+    pub fn set_grayscale(&mut self, on: bool) {
+        if self.grayscale != on {
+            self.grayscale = on;
+            self.gray_plane = 0;
+            self.gray_plane_end = Instant::now() + self.gray_slot * plane_slots(0);
+            self.update = true;
+        }
+    }
+
+    fn next_gray_plane(&mut self) {
+        self.gray_plane = (self.gray_plane + 1) % GRAY_BITS;
+        self.gray_plane_end = Instant::now() + self.gray_slot * plane_slots(self.gray_plane);
+        self.update = true;
+    }
+
+    // time left until the next bit plane has to be shown
+    fn gray_timeout(&self) -> Option<Duration> {
+        self.grayscale.then(|| self.gray_plane_end.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn play_animation(&mut self, animation: Animation) {
+        self.animation = Some(AnimationPlayer::new(animation));
+        self.show_animation_frame();
+    }
+
+    pub fn stop_animation(&mut self) {
+        self.animation = None;
+    }
+
+    fn show_animation_frame(&mut self) {
+        let Some(frame) = self.animation.as_ref().and_then(|player| player.current()).cloned() else {
+            self.animation = None;
+            return;
+        };
+
+        for (device, rows) in frame.devices.into_iter().enumerate() {
+            self.set_frame(device, rows);
+        }
+        for (device, levels) in frame.gray.into_iter().enumerate() {
+            self.set_gray_frame(device, levels);
+        }
+        self.animation_frame_end = Instant::now() + frame.duration;
+    }
+
+    fn animation_step(&mut self) {
+        let Some(player) = &mut self.animation else {
+            return;
+        };
+
+        if player.advance() {
+            self.show_animation_frame();
+        } else {
+            self.animation = None;
+        }
+    }
+
+    // time left until the next animation frame
+    fn animation_timeout(&self) -> Option<Duration> {
+        self.animation.as_ref().map(|_| self.animation_frame_end.saturating_duration_since(Instant::now()))
+    }
+
+    // framebuffers to show, in grayscale mode the current bit plane of the gray frames
+    fn framebuffer(&self) -> [[u8; 8]; N] {
+        if self.grayscale {
+            self.gray_states.map(|levels| bit_plane(&levels, self.gray_plane))
+        } else {
+            self.led_states
+        }
+    }
+
+    // framebuffers as shown by the devices, with the device transforms applied
+    fn device_rows(&self) -> [[u8; 8]; N] {
+        let mut rows = self.framebuffer();
+        for (rows, transform) in rows.iter_mut().zip(self.transforms.iter()) {
+            *rows = transform.apply(rows);
+        }
+        rows
+    }
+
+    // send rows which differ from what the devices show, devices with unchanged row get a no-op
+    async fn flush(&mut self) -> Result<(), T::Error> {
+        let device_rows = self.device_rows();
+
+        for row in 0..8 {
+            let mut commands = [Command::NO_OP; N];
+            let mut dirty = false;
+
+            for (device, rows) in device_rows.iter().enumerate() {
+                let data = rows[row];
+                if data != self.shown_rows[device][row] {
+                    commands[device] = Command::digit(row as u8, data).unwrap();
+                    dirty = true;
+                }
+            }
+
+            if dirty {
+                self.write_commands(commands).await?;
+                for (shown, rows) in self.shown_rows.iter_mut().zip(device_rows.iter()) {
+                    shown[row] = rows[row];
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_intensity(&mut self, device: usize, intensity: u8) -> Result<(), T::Error> {
+        if device >= N {
+            return Ok(());
+        }
+
+        let command = match Command::intensity(intensity) {
+            Ok(command) => command,
+            Err(err) => {
+                log::warn!("Max7219 set intensity failed: {:?}", err);
+                return Ok(());
+            }
+        };
+
+        self.intensity[device] = intensity;
+        self.write_device(device, command).await
+    }
+
+    pub async fn set_power(&mut self, on: bool) -> Result<(), T::Error> {
+        self.power_on = on;
+        self.write_all(Command::power(on)).await
+    }
+
+    pub async fn set_display_test(&mut self, on: bool) -> Result<(), T::Error> {
+        self.display_test = on;
+        self.write_all(Command::display_test(on)).await
+    }
+
+    // send one frame, commands[i] goes to device i
+    async fn write_commands(&mut self, commands: [Command; N]) -> Result<(), T::Error> {
+        cascade_frame(&commands, &mut self.frame);
+        self.spi.write(&self.frame).await
+    }
+
+    // send one frame, data[i] is written to the row of device i
+    async fn write_rows(&mut self, row: u8, data: [u8; N]) -> Result<(), T::Error> {
+        let mut commands = [Command::NO_OP; N];
+        for (command, data) in commands.iter_mut().zip(data) {
+            *command = Command::digit(row, data).unwrap();
+        }
+        self.write_commands(commands).await
+    }
+
+    // write register of a single device, other devices in the chain get a no-op
+    async fn write_device(&mut self, device: usize, command: Command) -> Result<(), T::Error> {
+        let mut commands = [Command::NO_OP; N];
+        commands[device] = command;
+        self.write_commands(commands).await
+    }
+
+    // send the same command to all devices
+    async fn write_all(&mut self, command: Command) -> Result<(), T::Error> {
+        self.write_commands([command; N]).await
+    }
+
+    async fn handle_action(&mut self, action: Max7219Action) -> Result<(), T::Error> {
+        match action {
+            Max7219Action::ClearScreen => {
+                    self.stop_animation();
+                    self.led_states = [[0; 8]; N];
+                    self.gray_states = [[[0; 8]; 8]; N];
+                    self.update = true;
+                }
+            Max7219Action::SetLedState { device, x, y, on } => {
+                self.set_led(device, x, y, on);
+            }
+            Max7219Action::SetFrame { device, rows } => {
+                self.set_frame(device, rows);
+            }
+            Max7219Action::SetIntensity { device, intensity } => {
+                self.set_intensity(device, intensity).await?;
+            }
+            Max7219Action::SetPower { on } => {
+                self.set_power(on).await?;
+            }
+            Max7219Action::SetDisplayTest { on } => {
+                self.set_display_test(on).await?;
+            }
+            Max7219Action::SetTransform { device, transform } => {
+                self.set_transform(device, transform);
+            }
+            Max7219Action::Reinit => {
+                self.init().await?;
+            }
+            Max7219Action::SetGrayFrame { device, levels } => {
+                self.set_gray_frame(device, levels);
+            }
+            Max7219Action::SetGrayscale { on } => {
+                self.set_grayscale(on);
+            }
+            Max7219Action::ScrollText { text, font, period } => {
+                self.play_animation(scroll_animation(&text, font, N, period));
+            }
+            Max7219Action::PlayAnimation(animation) => {
+                self.play_animation(animation);
+            }
+            Max7219Action::StopAnimation => {
+                self.stop_animation();
+            }
+        }
+        Ok(())
+    }
+
+    // time left until the next periodic refresh
+    fn refresh_timeout(&self) -> Option<Duration> {
+        match self.refresh {
+            RefreshPolicy::Never => None,
+            RefreshPolicy::Every(period) => Some(period.saturating_sub(self.last_refresh.elapsed())),
+        }
+    }
+
+    // wait for the next action, returns None when the timeout elapsed first
+    async fn next_action(&self, timeout: Option<Duration>) -> Option<Max7219Action> {
+        match (&self.client, timeout) {
+            (Some(client), Some(timeout)) => {
+                match select(pin!(client.recv()), Delay::new(timeout)).await {
+                    Either::Left((action, _)) => Some(action.unwrap()),
+                    Either::Right(_) => None,
+                }
+            }
+            (Some(client), None) => Some(client.recv().await.unwrap()),
+            (None, Some(timeout)) => {
+                Delay::new(timeout).await;
+                None
+            }
+            (None, None) => futures::future::pending().await,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        log::info!("Max7219 started");
+
+        for device in 0..N {
+            self.set_led(device, 1, 1, true);
+            self.set_led(device, 1, 7, true);
+        }
+
+        loop {
+
+            if self.grayscale && Instant::now() >= self.gray_plane_end {
+                self.next_gray_plane();
+            }
+
+            if self.animation.is_some() && Instant::now() >= self.animation_frame_end {
+                self.animation_step();
+            }
+
+            if self.update {
+                self.flush().await.unwrap();
+                self.update = false;
+            }
+
+            if self.refresh_timeout().is_some_and(|timeout| timeout.is_zero()) {
+                self.refresh().await.unwrap();
+            }
+
+            let timeout = earliest(earliest(self.refresh_timeout(), self.gray_timeout()), self.animation_timeout());
+            if let Some(action) = self.next_action(timeout).await {
+                self.handle_action(action).await.unwrap();
+            }
+        }
+    }
+
+    pub async fn run_demo(&mut self) {
+        log::info!("Max7219 demo started");
+
+        self.play_animation(demo_animation(N));
+        self.run().await;
+    }
+
+    async fn write_control_registers(&mut self) -> Result<(), T::Error> {
+        self.write_all(Command::display_test(self.display_test)).await?;
+        self.write_commands(self.intensity.map(|intensity| Command::intensity(intensity).unwrap())).await?;
+        self.write_all(Command::decode_mode(0x00)).await?;  // no decoding, raw led rows
+        self.write_all(Command::scan_limit(7).unwrap()).await?;  // scan all 8 rows
+        Ok(())
+    }
+
+    // rewrite all registers and the whole framebuffer without blanking the display
+    pub async fn refresh(&mut self) -> Result<(), T::Error> {
+        self.write_control_registers().await?;
+
+        let device_rows = self.device_rows();
+        for row in 0..8 {
+            self.write_rows(row, device_rows.map(|rows| rows[row as usize])).await?;
+        }
+        self.shown_rows = device_rows;
+
+        self.write_all(Command::power(self.power_on)).await?;
+        self.last_refresh = Instant::now();
+
+        Ok(())
+    }
+
+    pub async fn init(&mut self) -> Result<(), T::Error> {
+        self.write_all(Command::power(false)).await?;
+        self.write_control_registers().await?;
+
+        // Clear the LED matrix row by row
+        for row in 0..8 {
+            self.write_rows(row, [0; N]).await?;
+        }
+        self.shown_rows = [[0; 8]; N];
+        self.update = true;
+        self.write_all(Command::power(self.power_on)).await?;
+        self.last_refresh = Instant::now();
+
+        log::info!("Max7219 init done ({} devices)", N);
+
+        Ok(())
+    }
+}
+
+
+fn earliest(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+
+// diagonal line drawn row by row and erased again, odd devices show it mirrored
+fn demo_animation(devices: usize) -> Animation {
+    let mut animation = Animation::new(PlayMode::PingPong);
+    let mut rows = vec![[0u8; 8]; devices];
+
+    for row in 0..8 {
+        for (device, device_rows) in rows.iter_mut().enumerate() {
+            device_rows[row] = if device % 2 == 0 { 1 << row } else { 0x80 >> row };
+        }
+        let duration = if row == 7 { Duration::from_millis(1000) } else { Duration::from_millis(300) };
+        animation = animation.frame(rows.clone(), duration);
+    }
+
+    animation
+}
@@ -0,0 +1,134 @@
+// MAX7219 register addresses
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(u8)]
+pub enum Register {
+    NoOp = 0x00,
+    Digit0 = 0x01,
+    Digit1 = 0x02,
+    Digit2 = 0x03,
+    Digit3 = 0x04,
+    Digit4 = 0x05,
+    Digit5 = 0x06,
+    Digit6 = 0x07,
+    Digit7 = 0x08,
+    DecodeMode = 0x09,
+    Intensity = 0x0A,
+    ScanLimit = 0x0B,
+    Shutdown = 0x0C,
+    DisplayTest = 0x0F,
+}
+
+pub const DIGIT_REGISTERS: [Register; 8] = [
+    Register::Digit0, Register::Digit1, Register::Digit2, Register::Digit3,
+    Register::Digit4, Register::Digit5, Register::Digit6, Register::Digit7,
+];
+
+pub const MAX_INTENSITY: u8 = 0x0F;
+pub const MAX_SCAN_LIMIT: u8 = 0x07;
+
+impl Register {
+
+    // register of the given row (digit) of the led matrix
+    pub fn digit(row: u8) -> Option<Register> {
+        DIGIT_REGISTERS.get(row as usize).copied()
+    }
+
+    pub fn addr(self) -> u8 {
+        self as u8
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CommandError {
+    Digit(u8),
+    Intensity(u8),
+    ScanLimit(u8),
+}
+
+
+// Register write for a single device in the chain.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Command {
+    register: Register,
+    data: u8,
+}
+
+impl Command {
+
+    // command which is ignored by the device, used to skip devices in a cascade frame
+    pub const NO_OP: Command = Command { register: Register::NoOp, data: 0x00 };
+
+    pub fn digit(row: u8, data: u8) -> Result<Command, CommandError> {
+        let register = Register::digit(row).ok_or(CommandError::Digit(row))?;
+        Ok(Command { register, data })
+    }
+
+    pub fn intensity(intensity: u8) -> Result<Command, CommandError> {
+        if intensity > MAX_INTENSITY {
+            return Err(CommandError::Intensity(intensity));
+        }
+        Ok(Command { register: Register::Intensity, data: intensity })
+    }
+
+    // number of scanned digits minus one
+    pub fn scan_limit(limit: u8) -> Result<Command, CommandError> {
+        if limit > MAX_SCAN_LIMIT {
+            return Err(CommandError::ScanLimit(limit));
+        }
+        Ok(Command { register: Register::ScanLimit, data: limit })
+    }
+
+    // bit n enables code B decoding of digit n, led matrices use no decoding (0x00)
+    pub fn decode_mode(digits: u8) -> Command {
+        Command { register: Register::DecodeMode, data: digits }
+    }
+
+    // normal operation when on, shutdown mode otherwise
+    pub fn power(on: bool) -> Command {
+        Command { register: Register::Shutdown, data: on as u8 }
+    }
+
+    pub fn display_test(on: bool) -> Command {
+        Command { register: Register::DisplayTest, data: on as u8 }
+    }
+}
+
+
+// Build SPI frame for the chain, commands[i] goes to device i. Device 0 is the first in the
+// chain, so its command is shifted out last and ends up at the end of the frame.
+pub fn cascade_frame(commands: &[Command], frame: &mut Vec<u8>) {
+    frame.clear();
+    for command in commands.iter().rev() {
+        frame.push(command.register.addr());
+        frame.push(command.data);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_commands_are_rejected() {
+        assert_eq!(Command::intensity(16), Err(CommandError::Intensity(16)));
+        assert_eq!(Command::scan_limit(8), Err(CommandError::ScanLimit(8)));
+        assert_eq!(Command::digit(8, 0xFF), Err(CommandError::Digit(8)));
+    }
+
+    #[test]
+    fn limits_are_accepted() {
+        assert_eq!(Command::intensity(MAX_INTENSITY).unwrap().data, MAX_INTENSITY);
+        assert_eq!(Command::scan_limit(MAX_SCAN_LIMIT).unwrap().register, Register::ScanLimit);
+        assert_eq!(Command::digit(7, 0xAA).unwrap().register, Register::Digit7);
+    }
+
+    #[test]
+    fn device_zero_is_last_in_the_frame() {
+        let commands = [Command::digit(0, 0x11).unwrap(), Command::NO_OP, Command::intensity(3).unwrap()];
+        let mut frame = Vec::new();
+        cascade_frame(&commands, &mut frame);
+        assert_eq!(frame, vec![0x0A, 0x03, 0x00, 0x00, 0x01, 0x11]);
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+use super::font::Font;
+use super::animation::{Animation, PlayMode};
+
+
+// Renders text into led columns, bit y of a column is the led in row y. Glyphs are
+// separated by one empty column and centered vertically on the 8 led rows.
+pub fn render_columns(text: &str, font: Font) -> Vec<u8> {
+    let row_offset = (8 - font.height()) / 2;
+    let mut columns = Vec::new();
+
+    for (idx, c) in text.chars().enumerate() {
+        if idx > 0 {
+            columns.push(0);
+        }
+        columns.extend(font.glyph(c).iter().map(|column| column << row_offset));
+    }
+
+    columns
+}
+
+// framebuffers of a chain of devices placed left to right showing the columns starting
+// from offset, device i shows columns offset + 8i up to offset + 8i + 7
+pub fn columns_to_frames(columns: &[u8], offset: i32, devices: usize) -> Vec<[u8; 8]> {
+    let mut frames = vec![[0u8; 8]; devices];
+
+    for (device, rows) in frames.iter_mut().enumerate() {
+        for x in 0..8 {
+            let idx = offset + (8 * device + x) as i32;
+            let Some(column) = usize::try_from(idx).ok().and_then(|idx| columns.get(idx)) else {
+                continue;
+            };
+            for (y, row) in rows.iter_mut().enumerate() {
+                if column & (1 << y) != 0 {
+                    *row |= 1 << x;
+                }
+            }
+        }
+    }
+
+    frames
+}
+
+// framebuffers showing the text from the left edge of the chain, e.g. remaining minutes
+pub fn text_frames<const N: usize>(text: &str, font: Font) -> [[u8; 8]; N] {
+    let mut frames = [[0u8; 8]; N];
+    frames.copy_from_slice(&columns_to_frames(&render_columns(text, font), 0, N));
+    frames
+}
+
+// animation moving the text from the right edge of the chain until it leaves at the
+// left edge, one column every period
+pub fn scroll_animation(text: &str, font: Font, devices: usize, period: Duration) -> Animation {
+    let columns = render_columns(text, font);
+    let width = 8 * devices as i32;
+
+    (-width..=columns.len() as i32).fold(Animation::new(PlayMode::Once), |animation, offset| {
+        animation.frame(columns_to_frames(&columns, offset, devices), period)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // column with the top led (row 0) and the bottom led (row 7) lit
+    const COLUMNS: [u8; 2] = [0x01, 0x80];
+
+    #[test]
+    fn glyphs_are_separated_and_centered() {
+        assert_eq!(render_columns("1A", Font::Font3x5), vec![0x24, 0x3E, 0x20, 0, 0x3C, 0x0A, 0x3C]);
+        assert_eq!(render_columns("1", Font::Font5x7), vec![0x00, 0x42, 0x7F, 0x40, 0x00]);
+        assert!(render_columns("", Font::Font5x7).is_empty());
+    }
+
+    #[test]
+    fn columns_are_split_between_devices() {
+        assert_eq!(columns_to_frames(&COLUMNS, 0, 2), vec![[1, 0, 0, 0, 0, 0, 0, 2], [0; 8]]);
+        assert_eq!(columns_to_frames(&COLUMNS, 1, 2), vec![[0, 0, 0, 0, 0, 0, 0, 1], [0; 8]]);
+        assert_eq!(columns_to_frames(&COLUMNS, -7, 2), vec![[0x80, 0, 0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 0, 0, 0, 1]]);
+        assert_eq!(columns_to_frames(&COLUMNS, -8, 2), vec![[0; 8], [1, 0, 0, 0, 0, 0, 0, 2]]);
+    }
+
+    #[test]
+    fn columns_outside_of_the_chain_are_not_shown() {
+        assert_eq!(columns_to_frames(&COLUMNS, -16, 2), vec![[0; 8]; 2]);
+        assert_eq!(columns_to_frames(&COLUMNS, 2, 2), vec![[0; 8]; 2]);
+        assert_eq!(columns_to_frames(&COLUMNS, 100, 2), vec![[0; 8]; 2]);
+    }
+
+    #[test]
+    fn text_frames_start_at_the_left_edge() {
+        let frames: [[u8; 8]; 2] = text_frames("1", Font::Font3x5);
+        assert_eq!(frames[0], [0, 0b010, 0b011, 0b010, 0b010, 0b111, 0, 0]);
+        assert_eq!(frames[1], [0; 8]);
+    }
+
+    #[test]
+    fn scroll_moves_text_across_the_whole_chain() {
+        let period = Duration::from_millis(50);
+        let animation = scroll_animation("A", Font::Font5x7, 2, period);
+
+        // from just outside the right edge until the last column left the chain
+        assert_eq!(animation.mode, PlayMode::Once);
+        assert_eq!(animation.frames.len(), 16 + 5 + 1);
+        assert_eq!(animation.duration(), period * 22);
+        assert_eq!(animation.frames.first().unwrap().devices, vec![[0; 8]; 2]);
+        assert_eq!(animation.frames.last().unwrap().devices, vec![[0; 8]; 2]);
+        // the first column enters at the right edge of device 1
+        assert_eq!(animation.frames[1].devices[0], [0; 8]);
+        assert_eq!(animation.frames[1].devices[1].map(|row| row >> 7), [0, 1, 1, 1, 1, 1, 1, 0]);
+    }
+}
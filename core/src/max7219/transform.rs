@@ -0,0 +1,92 @@
+// clockwise rotation of the led matrix as mounted on the board
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+
+// Mapping from logical led coordinates to the leds of the device. Flips are applied
+// in logical coordinates first, the rotation afterwards.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Transform {
+    pub rotation: Rotation,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Transform {
+
+    pub fn map(&self, x: u8, y: u8) -> (u8, u8) {
+        let x = if self.flip_x { 7 - x } else { x };
+        let y = if self.flip_y { 7 - y } else { y };
+
+        match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (7 - y, x),
+            Rotation::Deg180 => (7 - x, 7 - y),
+            Rotation::Deg270 => (y, 7 - x),
+        }
+    }
+
+    // rows of the framebuffer in logical coordinates to rows of the device
+    pub fn apply(&self, rows: &[u8; 8]) -> [u8; 8] {
+        if *self == Transform::default() {
+            return *rows;
+        }
+
+        let mut out = [0u8; 8];
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..8 {
+                if row & (1 << x) != 0 {
+                    let (tx, ty) = self.map(x, y as u8);
+                    out[ty as usize] |= 1 << tx;
+                }
+            }
+        }
+        out
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // single led (1, 0)
+    const ROWS: [u8; 8] = [0b10, 0, 0, 0, 0, 0, 0, 0];
+
+    fn transform(rotation: Rotation, flip_x: bool, flip_y: bool) -> [u8; 8] {
+        Transform { rotation, flip_x, flip_y }.apply(&ROWS)
+    }
+
+    fn led(x: u8, y: u8) -> [u8; 8] {
+        let mut rows = [0u8; 8];
+        rows[y as usize] = 1 << x;
+        rows
+    }
+
+    #[test]
+    fn rotations() {
+        assert_eq!(transform(Rotation::Deg0, false, false), led(1, 0));
+        assert_eq!(transform(Rotation::Deg90, false, false), led(7, 1));
+        assert_eq!(transform(Rotation::Deg180, false, false), led(6, 7));
+        assert_eq!(transform(Rotation::Deg270, false, false), led(0, 6));
+    }
+
+    #[test]
+    fn flips() {
+        assert_eq!(transform(Rotation::Deg0, true, false), led(6, 0));
+        assert_eq!(transform(Rotation::Deg0, false, true), led(1, 7));
+        assert_eq!(transform(Rotation::Deg0, true, true), led(6, 7));
+    }
+
+    #[test]
+    fn flip_is_applied_before_rotation() {
+        assert_eq!(transform(Rotation::Deg90, true, false), led(7, 6));
+        assert_eq!(transform(Rotation::Deg270, false, true), led(7, 6));
+    }
+}
@@ -0,0 +1,160 @@
+// Madgwick orientation filter for a 6 axis IMU. The orientation is kept as a quaternion,
+// so unlike per axis angles it has no singularity and stays valid through full rotations.
+// Each step integrates the gyroscope and corrects it by a gradient descent step of size
+// beta towards the orientation in which gravity points along the measured acceleration.
+pub struct Madgwick {
+    beta: f32,
+    // (w, x, y, z), rotation of the sensor frame relative to the earth frame
+    q: (f32, f32, f32, f32),
+    initialized: bool,
+}
+
+impl Madgwick {
+
+    pub fn new(beta: f32) -> Self {
+        Self {
+            beta,
+            q: (1f32, 0f32, 0f32, 0f32),
+            initialized: false,
+        }
+    }
+
+    // start from the orientation given by gravity, yaw can't be measured so it starts at 0
+    fn init(&mut self, acc: (f32, f32, f32)) {
+        let roll = acc.1.atan2(acc.2);
+        let pitch = (-acc.0).atan2((acc.1 * acc.1 + acc.2 * acc.2).sqrt());
+        let (sr, cr) = (roll / 2f32).sin_cos();
+        let (sp, cp) = (pitch / 2f32).sin_cos();
+        self.q = (cr * cp, sr * cp, cr * sp, -sr * sp);
+        self.initialized = true;
+    }
+
+    // gyro in deg/s, acc in any unit, dt in seconds
+    pub fn update(&mut self, gyro: (f32, f32, f32), acc: (f32, f32, f32), dt: f32) {
+        let acc_norm = (acc.0 * acc.0 + acc.1 * acc.1 + acc.2 * acc.2).sqrt();
+        if !self.initialized && acc_norm > 0f32 {
+            self.init(acc);
+            return;
+        }
+
+        let (q0, q1, q2, q3) = self.q;
+        let (gx, gy, gz) = (gyro.0.to_radians(), gyro.1.to_radians(), gyro.2.to_radians());
+
+        // rate of change of the quaternion from the gyroscope
+        let mut dq0 = 0.5f32 * (-q1 * gx - q2 * gy - q3 * gz);
+        let mut dq1 = 0.5f32 * (q0 * gx + q2 * gz - q3 * gy);
+        let mut dq2 = 0.5f32 * (q0 * gy - q1 * gz + q3 * gx);
+        let mut dq3 = 0.5f32 * (q0 * gz + q1 * gy - q2 * gx);
+
+        // in free fall there is no gravity to correct with
+        if acc_norm > 0f32 {
+            let (ax, ay, az) = (acc.0 / acc_norm, acc.1 / acc_norm, acc.2 / acc_norm);
+
+            // gradient of the error between estimated and measured direction of gravity
+            let s0 = 4f32 * q0 * q2 * q2 + 2f32 * q2 * ax + 4f32 * q0 * q1 * q1 - 2f32 * q1 * ay;
+            let s1 = 4f32 * q1 * q3 * q3 - 2f32 * q3 * ax + 4f32 * q0 * q0 * q1 - 2f32 * q0 * ay - 4f32 * q1
+                + 8f32 * q1 * q1 * q1 + 8f32 * q1 * q2 * q2 + 4f32 * q1 * az;
+            let s2 = 4f32 * q0 * q0 * q2 + 2f32 * q0 * ax + 4f32 * q2 * q3 * q3 - 2f32 * q3 * ay - 4f32 * q2
+                + 8f32 * q2 * q1 * q1 + 8f32 * q2 * q2 * q2 + 4f32 * q2 * az;
+            let s3 = 4f32 * q1 * q1 * q3 - 2f32 * q1 * ax + 4f32 * q2 * q2 * q3 - 2f32 * q2 * ay;
+
+            let s_norm = (s0 * s0 + s1 * s1 + s2 * s2 + s3 * s3).sqrt();
+            if s_norm > 0f32 {
+                dq0 -= self.beta * s0 / s_norm;
+                dq1 -= self.beta * s1 / s_norm;
+                dq2 -= self.beta * s2 / s_norm;
+                dq3 -= self.beta * s3 / s_norm;
+            }
+        }
+
+        let q = (q0 + dq0 * dt, q1 + dq1 * dt, q2 + dq2 * dt, q3 + dq3 * dt);
+        let q_norm = (q.0 * q.0 + q.1 * q.1 + q.2 * q.2 + q.3 * q.3).sqrt();
+        self.q = (q.0 / q_norm, q.1 / q_norm, q.2 / q_norm, q.3 / q_norm);
+    }
+
+    // unit vector pointing up in the sensor frame, what the accelerometer measures at rest
+    pub fn gravity(&self) -> (f32, f32, f32) {
+        let (q0, q1, q2, q3) = self.q;
+        (
+            2f32 * (q1 * q3 - q0 * q2),
+            2f32 * (q0 * q1 + q2 * q3),
+            q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3,
+        )
+    }
+
+    // roll, pitch and yaw in degrees
+    pub fn euler(&self) -> (f32, f32, f32) {
+        let (q0, q1, q2, q3) = self.q;
+        let roll = (q0 * q1 + q2 * q3).atan2(0.5f32 - q1 * q1 - q2 * q2);
+        let pitch = (2f32 * (q0 * q2 - q1 * q3)).clamp(-1f32, 1f32).asin();
+        let yaw = (q1 * q2 + q0 * q3).atan2(0.5f32 - q2 * q2 - q3 * q3);
+        (roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 90f32;
+    const DT: f32 = 0.01f32;
+    // the correction step always has the size beta, so the estimate wobbles around the input
+    const MAX_ANGLE_ERROR: f32 = 2f32;
+
+    fn angle_error(actual: f32, expected: f32) -> f32 {
+        ((actual - expected + 180f32).rem_euclid(360f32) - 180f32).abs()
+    }
+
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let error = (actual.0 - expected.0).abs().max((actual.1 - expected.1).abs()).max((actual.2 - expected.2).abs());
+        assert!(error < 0.035, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn tracks_full_rotation_around_x() {
+        let mut filter = Madgwick::new(0.5);
+        filter.update((0f32, 0f32, 0f32), (0f32, 0f32, 1f32), DT);
+
+        // turning by angle around x the gravity seen by the sensor is (0, sin, cos)
+        for step in 1..=400 {
+            let angle = RATE * DT * step as f32;
+            let (sin, cos) = angle.to_radians().sin_cos();
+            filter.update((RATE, 0f32, 0f32), (0f32, sin, cos), DT);
+
+            assert_close(filter.gravity(), (0f32, sin, cos));
+            let (roll, pitch, _) = filter.euler();
+            assert!(angle_error(roll, angle) < MAX_ANGLE_ERROR, "roll {} at {}", roll, angle);
+            assert!(pitch.abs() < MAX_ANGLE_ERROR);
+        }
+    }
+
+    #[test]
+    fn tracks_pitch_through_plus_minus_90_degrees() {
+        let mut filter = Madgwick::new(0.5);
+        filter.update((0f32, 0f32, 0f32), (0f32, 0f32, 1f32), DT);
+
+        // 0 -> 90 -> -90 -> 0 degrees around y, the gravity seen by the sensor is (-sin, 0, cos)
+        let mut angle = 0f32;
+        for rate in [RATE, -RATE, RATE] {
+            let steps = if rate < 0f32 { 200 } else { 100 };
+            for _ in 0..steps {
+                angle += rate * DT;
+                let (sin, cos) = angle.to_radians().sin_cos();
+                filter.update((0f32, rate, 0f32), (-sin, 0f32, cos), DT);
+
+                assert_close(filter.gravity(), (-sin, 0f32, cos));
+                let (_, pitch, _) = filter.euler();
+                assert!((pitch - angle).abs() < MAX_ANGLE_ERROR, "pitch {} at {}", pitch, angle);
+            }
+        }
+    }
+
+    #[test]
+    fn starts_from_measured_gravity() {
+        let mut filter = Madgwick::new(0.5);
+        filter.update((0f32, 0f32, 0f32), (0f32, 0f32, -1f32), DT);
+        assert_close(filter.gravity(), (0f32, 0f32, -1f32));
+        assert!(angle_error(filter.euler().0, 180f32) < 0.1);
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+
+// Full scale range of the accelerometer, ACCEL_CONFIG (0x1C) AFS_SEL bits
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum AccelRange {
+    #[default]
+    G2,
+    G4,
+    G8,
+    G16,
+}
+
+impl AccelRange {
+    pub fn register_value(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    // LSB per g
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            AccelRange::G2 => 16384f32,
+            AccelRange::G4 => 8192f32,
+            AccelRange::G8 => 4096f32,
+            AccelRange::G16 => 2048f32,
+        }
+    }
+}
+
+
+// Full scale range of the gyroscope, GYRO_CONFIG (0x1B) FS_SEL bits
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GyroRange {
+    #[default]
+    Deg250,
+    Deg500,
+    Deg1000,
+    Deg2000,
+}
+
+impl GyroRange {
+    pub fn register_value(self) -> u8 {
+        (self as u8) << 3
+    }
+
+    // LSB per deg/s
+    pub fn sensitivity(self) -> f32 {
+        match self {
+            GyroRange::Deg250 => 131f32,
+            GyroRange::Deg500 => 65.5f32,
+            GyroRange::Deg1000 => 32.8f32,
+            GyroRange::Deg2000 => 16.4f32,
+        }
+    }
+}
+
+
+// Digital low pass filter, CONFIG (0x1A) DLPF_CFG bits. Named after the accelerometer
+// bandwidth in Hz, the gyroscope one is nearly the same.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum DlpfBandwidth {
+    #[default]
+    Hz260,
+    Hz184,
+    Hz94,
+    Hz44,
+    Hz21,
+    Hz10,
+    Hz5,
+}
+
+impl DlpfBandwidth {
+    pub fn register_value(self) -> u8 {
+        self as u8
+    }
+
+    // gyroscope output rate the sample rate divider is applied to
+    pub fn gyro_output_rate(self) -> f32 {
+        match self {
+            DlpfBandwidth::Hz260 => 8000f32,
+            _ => 1000f32,
+        }
+    }
+}
+
+
+// How gyroscope and accelerometer are fused into the orientation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OrientationFilter {
+    // per axis angles, coefficient is the weight of the integrated gyroscope angle and the
+    // rest is taken from the accelerometer angle, breaks down near +-90 deg of pitch
+    Complementary { coefficient: f32 },
+    // quaternion based AHRS valid in any orientation, beta is the accelerometer correction
+    // gain, higher converges faster but lets more acceleration noise through
+    Madgwick { beta: f32 },
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mpu6050Config {
+    pub accel_range: AccelRange,
+    pub gyro_range: GyroRange,
+    pub dlpf: DlpfBandwidth,
+    // SMPLRT_DIV (0x19), sample rate = gyroscope output rate / (1 + divider)
+    pub sample_rate_divider: u8,
+    // collect every sample in the hardware fifo instead of reading only the latest one
+    pub fifo: bool,
+    pub filter: OrientationFilter,
+    // pause after the calibration before sampling starts, leaves time to read the calibration log
+    pub calibration_pause: Duration,
+}
+
+impl Default for Mpu6050Config {
+    // sensor registers as after power up
+    fn default() -> Self {
+        Self {
+            accel_range: AccelRange::default(),
+            gyro_range: GyroRange::default(),
+            dlpf: DlpfBandwidth::default(),
+            sample_rate_divider: 0,
+            fifo: false,
+            filter: OrientationFilter::Complementary { coefficient: 0.96 },
+            calibration_pause: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Mpu6050Config {
+    // rate in Hz at which the sensor registers are updated
+    pub fn sample_rate(&self) -> f32 {
+        self.dlpf.gyro_output_rate() / (1f32 + self.sample_rate_divider as f32)
+    }
+
+    pub fn sample_period(&self) -> Duration {
+        Duration::from_secs_f32(1f32 / self.sample_rate())
+    }
+}
@@ -0,0 +1,600 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::convert::Infallible;
+use futures_timer::Delay;
+use crate::i2c::I2cTransportInterface;
+use crate::interrupt::InterruptPinInterface;
+use super::{
+    DEFAULT_ADDRESS, REG_SMPLRT_DIV, REG_CONFIG, REG_GYRO_CONFIG, REG_ACCEL_CONFIG, REG_FIFO_EN,
+    REG_INT_ENABLE, REG_INT_STATUS, REG_ACCEL_XOUT_H, REG_USER_CTRL, REG_PWR_MGMT_1, REG_FIFO_COUNT_H,
+    REG_FIFO_R_W, FIFO_SIZE, USER_CTRL_FIFO_EN, USER_CTRL_FIFO_RESET, INT_FIFO_OFLOW, INT_DATA_RDY,
+};
+
+// registers only the emulator needs
+const REG_FIFO_COUNT_L: u8 = REG_FIFO_COUNT_H + 1;
+const REG_WHO_AM_I: u8 = 0x75;
+const PWR_MGMT_1_SLEEP: u8 = 0x40;
+
+
+// Rotation of the emulated device with a constant angular rate (deg/s around sensor axes).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MotionStep {
+    pub rotation: (f32, f32, f32),
+    pub duration: Duration,
+}
+
+
+struct EmulatorState {
+    registers: [u8; 128],
+    // register pointer, incremented after every byte read or written
+    pointer: u8,
+    // gravity direction in the sensor frame (in g)
+    gravity: (f32, f32, f32),
+    // current angular rate in deg/s
+    rotation: (f32, f32, f32),
+    script: Vec<MotionStep>,
+    step_end: Option<Instant>,
+    accel_noise: f32,
+    gyro_noise: f32,
+    gyro_bias: (f32, f32, f32),
+    temperature: f32,
+    seed: u32,
+    updated: Instant,
+    fifo: VecDeque<u8>,
+    // time of the last sample pushed to the fifo
+    fifo_time: Instant,
+    // samples are taken every sample period counted from power up
+    epoch: Instant,
+}
+
+
+// MPU6050 register map emulator answering I2C transfers on DEFAULT_ADDRESS. Sensor data
+// registers are computed from the emulated orientation whenever they are read. Clones
+// share the state, so the orientation can be changed while the driver owns the transport.
+#[derive(Clone)]
+pub struct Mpu6050Emulator {
+    state: Arc<Mutex<EmulatorState>>,
+}
+
+impl Default for Mpu6050Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mpu6050Emulator {
+
+    // device lying flat, z axis pointing up
+    pub fn new() -> Self {
+        let mut registers = [0u8; 128];
+        registers[REG_PWR_MGMT_1 as usize] = PWR_MGMT_1_SLEEP; // sleep after power up
+        registers[REG_WHO_AM_I as usize] = DEFAULT_ADDRESS;
+
+        Self {
+            state: Arc::new(Mutex::new(EmulatorState {
+                registers,
+                pointer: 0,
+                gravity: (0f32, 0f32, 1f32),
+                rotation: (0f32, 0f32, 0f32),
+                script: Vec::new(),
+                step_end: None,
+                accel_noise: 0f32,
+                gyro_noise: 0f32,
+                gyro_bias: (0f32, 0f32, 0f32),
+                temperature: 25f32,
+                seed: 0x1234_5678,
+                updated: Instant::now(),
+                fifo: VecDeque::new(),
+                fifo_time: Instant::now(),
+                epoch: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn set_gravity(&self, gravity: (f32, f32, f32)) {
+        let mut state = self.state.lock().unwrap();
+        state.advance(Instant::now());
+        state.gravity = normalize(gravity);
+    }
+
+    pub fn gravity(&self) -> (f32, f32, f32) {
+        let mut state = self.state.lock().unwrap();
+        state.advance(Instant::now());
+        state.gravity
+    }
+
+    // constant angular rate in deg/s, replaces a running script
+    pub fn set_rotation(&self, rotation: (f32, f32, f32)) {
+        let mut state = self.state.lock().unwrap();
+        state.advance(Instant::now());
+        state.script.clear();
+        state.step_end = None;
+        state.rotation = rotation;
+    }
+
+    // run the steps one after another, the device stops rotating after the last one
+    pub fn set_script(&self, script: Vec<MotionStep>) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.advance(now);
+        state.script = script;
+        state.script.reverse();
+        state.rotation = (0f32, 0f32, 0f32);
+        state.step_end = Some(now);
+    }
+
+    // peak amplitude of uniform noise added to accelerometer (g) and gyroscope (deg/s) readings
+    pub fn set_noise(&self, accel_noise: f32, gyro_noise: f32) {
+        let mut state = self.state.lock().unwrap();
+        state.accel_noise = accel_noise;
+        state.gyro_noise = gyro_noise;
+    }
+
+    pub fn set_gyro_bias(&self, gyro_bias: (f32, f32, f32)) {
+        self.state.lock().unwrap().gyro_bias = gyro_bias;
+    }
+
+    pub fn set_temperature(&self, temperature: f32) {
+        self.state.lock().unwrap().temperature = temperature;
+    }
+
+    pub fn register(&self, register: u8) -> u8 {
+        self.state.lock().unwrap().registers[register as usize & 0x7F]
+    }
+
+    // INT pin of the emulated device, pulses on every sample when data ready interrupt is enabled
+    pub fn interrupt_pin(&self) -> Mpu6050InterruptPin {
+        Mpu6050InterruptPin { state: self.state.clone() }
+    }
+}
+
+
+pub struct Mpu6050InterruptPin {
+    state: Arc<Mutex<EmulatorState>>,
+}
+
+impl InterruptPinInterface for Mpu6050InterruptPin {
+    type Error = Infallible;
+
+    async fn wait_for_interrupt(&mut self) -> Result<(), Infallible> {
+        loop {
+            let now = Instant::now();
+            let next = {
+                let state = self.state.lock().unwrap();
+                state.sample_time(now) + state.sample_period()
+            };
+            Delay::new(next.saturating_duration_since(now)).await;
+
+            let mut state = self.state.lock().unwrap();
+            if !state.is_sleeping() && state.registers[REG_INT_ENABLE as usize] & INT_DATA_RDY != 0 {
+                state.registers[REG_INT_STATUS as usize] |= INT_DATA_RDY;
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl EmulatorState {
+
+    // bring the fifo and the orientation up to the given time, fifo samples are taken first
+    // because the orientation can't be integrated backwards
+    fn advance(&mut self, now: Instant) {
+        self.update_fifo(now);
+        self.update(now);
+    }
+
+    // rotate the gravity vector by the angular rate since the last update
+    fn update(&mut self, now: Instant) {
+        while let Some(step_end) = self.step_end {
+            if step_end > now {
+                break;
+            }
+            self.integrate(step_end);
+            match self.script.pop() {
+                Some(step) => {
+                    self.rotation = step.rotation;
+                    self.step_end = Some(step_end + step.duration);
+                }
+                None => {
+                    self.rotation = (0f32, 0f32, 0f32);
+                    self.step_end = None;
+                }
+            }
+        }
+        self.integrate(now);
+    }
+
+    fn integrate(&mut self, now: Instant) {
+        let dt = now.saturating_duration_since(self.updated).as_secs_f32();
+        self.updated = now;
+
+        // device rotates by w, so gravity seen by the sensor rotates by -w: dg/dt = g x w
+        let w = scale(self.rotation, std::f32::consts::PI / 180f32);
+        let g = self.gravity;
+        let dg = (g.1 * w.2 - g.2 * w.1, g.2 * w.0 - g.0 * w.2, g.0 * w.1 - g.1 * w.0);
+        self.gravity = normalize((g.0 + dg.0 * dt, g.1 + dg.1 * dt, g.2 + dg.2 * dt));
+    }
+
+    // uniform noise in range -amplitude..amplitude
+    fn noise(&mut self, amplitude: f32) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        amplitude * ((self.seed as f32 / u32::MAX as f32) * 2f32 - 1f32)
+    }
+
+    // raw accelerometer, temperature and gyroscope values in the ACCEL_XOUT_H..GYRO_ZOUT_L order
+    fn sensor_values(&mut self) -> [i16; 7] {
+        // LSB per g and per deg/s for the full scale range selected in the config registers
+        let accel_sensitivity = 16384f32 / (1 << ((self.registers[REG_ACCEL_CONFIG as usize] >> 3) & 0x03)) as f32;
+        // gyroscope sensitivities are rounded in the datasheet, they are not exact halves
+        let gyro_sensitivity = [131f32, 65.5f32, 32.8f32, 16.4f32][((self.registers[REG_GYRO_CONFIG as usize] >> 3) & 0x03) as usize];
+
+        let accel = self.gravity;
+        let accel = [accel.0, accel.1, accel.2].map(|a| a + self.noise(self.accel_noise));
+        let gyro = [
+            self.rotation.0 + self.gyro_bias.0,
+            self.rotation.1 + self.gyro_bias.1,
+            self.rotation.2 + self.gyro_bias.2,
+        ].map(|g| g + self.noise(self.gyro_noise));
+        let temperature = (self.temperature - 36.53f32) * 340f32;
+
+        let values = [
+            accel[0] * accel_sensitivity, accel[1] * accel_sensitivity, accel[2] * accel_sensitivity,
+            temperature,
+            gyro[0] * gyro_sensitivity, gyro[1] * gyro_sensitivity, gyro[2] * gyro_sensitivity,
+        ];
+        values.map(|value| value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+
+    fn is_sleeping(&self) -> bool {
+        self.registers[REG_PWR_MGMT_1 as usize] & PWR_MGMT_1_SLEEP != 0
+    }
+
+    // refresh ACCEL_XOUT_H..GYRO_ZOUT_L from the emulated orientation
+    fn update_sensor_registers(&mut self) {
+        if self.is_sleeping() {
+            return;
+        }
+
+        self.advance(Instant::now());
+
+        for (idx, raw) in self.sensor_values().iter().enumerate() {
+            let reg = REG_ACCEL_XOUT_H as usize + 2 * idx;
+            self.registers[reg..reg + 2].copy_from_slice(&raw.to_be_bytes());
+        }
+    }
+
+    // sample rate selected by CONFIG and SMPLRT_DIV
+    fn sample_period(&self) -> Duration {
+        let gyro_output_rate = match self.registers[REG_CONFIG as usize] & 0x07 {
+            0 | 7 => 8000f32,
+            _ => 1000f32,
+        };
+        let rate = gyro_output_rate / (1f32 + self.registers[REG_SMPLRT_DIV as usize] as f32);
+        Duration::from_secs_f32(1f32 / rate)
+    }
+
+    // time of the last sample taken at or before now
+    fn sample_time(&self, now: Instant) -> Instant {
+        let period = self.sample_period().as_secs_f64();
+        let samples = (now.saturating_duration_since(self.epoch).as_secs_f64() / period).floor();
+        self.epoch + Duration::from_secs_f64(samples * period)
+    }
+
+    // push a sample of the sensors selected in FIFO_EN for every sample period since the last update
+    fn update_fifo(&mut self, now: Instant) {
+        if self.is_sleeping() || self.registers[REG_USER_CTRL as usize] & USER_CTRL_FIFO_EN == 0 {
+            self.fifo_time = self.sample_time(now);
+            return;
+        }
+
+        let period = self.sample_period();
+        while self.fifo_time + period <= now {
+            self.fifo_time += period;
+            self.update(self.fifo_time);
+            self.push_fifo_sample();
+        }
+    }
+
+    fn push_fifo_sample(&mut self) {
+        let values = self.sensor_values();
+        let enabled = self.registers[REG_FIFO_EN as usize];
+
+        // (FIFO_EN bit, index of the value), in the order the values are written to the fifo
+        let sources = [(0x08, 0), (0x08, 1), (0x08, 2), (0x80, 3), (0x40, 4), (0x20, 5), (0x10, 6)];
+        for (bit, idx) in sources {
+            if enabled & bit != 0 {
+                self.fifo.extend(values[idx].to_be_bytes());
+            }
+        }
+
+        // a full fifo drops the oldest data, FIFO_OFLOW_INT is raised only when it is enabled
+        if self.fifo.len() > FIFO_SIZE {
+            self.fifo.drain(..self.fifo.len() - FIFO_SIZE);
+            if self.registers[REG_INT_ENABLE as usize] & INT_FIFO_OFLOW != 0 {
+                self.registers[REG_INT_STATUS as usize] |= INT_FIFO_OFLOW;
+            }
+        }
+    }
+
+    // side effects of writing a register
+    fn written(&mut self, register: u8) {
+        let user_ctrl = self.registers[REG_USER_CTRL as usize];
+        if register == REG_USER_CTRL && user_ctrl & USER_CTRL_FIFO_RESET != 0 {
+            // FIFO_RESET clears itself, but empties the fifo only while FIFO_EN is 0
+            self.registers[REG_USER_CTRL as usize] &= !USER_CTRL_FIFO_RESET;
+            if user_ctrl & USER_CTRL_FIFO_EN == 0 {
+                self.fifo.clear();
+                self.fifo_time = self.sample_time(Instant::now());
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        let Some((register, data)) = data.split_first() else {
+            return;
+        };
+        // samples taken before the write still use the old configuration
+        self.advance(Instant::now());
+        self.pointer = *register & 0x7F;
+        for byte in data {
+            self.registers[self.pointer as usize] = *byte;
+            self.written(self.pointer);
+            self.pointer = (self.pointer + 1) & 0x7F;
+        }
+    }
+
+    fn read(&mut self, output: &mut [u8]) {
+        self.update_sensor_registers();
+        for byte in output.iter_mut() {
+            *byte = match self.pointer {
+                REG_FIFO_COUNT_H => (self.fifo.len() >> 8) as u8,
+                REG_FIFO_COUNT_L => self.fifo.len() as u8,
+                REG_FIFO_R_W => self.fifo.pop_front().unwrap_or(0),
+                register => self.registers[register as usize],
+            };
+
+            match self.pointer {
+                // interrupt status is cleared by reading it
+                REG_INT_STATUS => self.registers[REG_INT_STATUS as usize] = 0,
+                // reads from FIFO_R_W keep popping the fifo instead of moving to the next register
+                REG_FIFO_R_W => continue,
+                _ => {}
+            }
+            self.pointer = (self.pointer + 1) & 0x7F;
+        }
+    }
+}
+
+impl I2cTransportInterface for Mpu6050Emulator {
+    type Error = AddressNack;
+
+    async fn write_read(&mut self, address: u8, data_to_write: &[u8], output: &mut [u8]) -> Result<(), AddressNack> {
+        check_address(address)?;
+        let mut state = self.state.lock().unwrap();
+        state.write(data_to_write);
+        state.read(output);
+        Ok(())
+    }
+
+    async fn write(&mut self, address: u8, data: &[u8]) -> Result<(), AddressNack> {
+        check_address(address)?;
+        self.state.lock().unwrap().write(data);
+        Ok(())
+    }
+
+    async fn read(&mut self, address: u8, output: &mut [u8]) -> Result<(), AddressNack> {
+        check_address(address)?;
+        self.state.lock().unwrap().read(output);
+        Ok(())
+    }
+}
+
+
+// no device acknowledges other addresses
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AddressNack(pub u8);
+
+fn check_address(address: u8) -> Result<(), AddressNack> {
+    if address == DEFAULT_ADDRESS {
+        Ok(())
+    } else {
+        Err(AddressNack(address))
+    }
+}
+
+fn scale(v: (f32, f32, f32), factor: f32) -> (f32, f32, f32) {
+    (v.0 * factor, v.1 * factor, v.2 * factor)
+}
+
+fn normalize(v: (f32, f32, f32)) -> (f32, f32, f32) {
+    let len = (v.0 * v.0 + v.1 * v.1 + v.2 * v.2).sqrt();
+    if len == 0f32 {
+        return (0f32, 0f32, 1f32);
+    }
+    scale(v, 1f32 / len)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+    use futures::executor::block_on;
+    use futures::future::select;
+    use super::*;
+    use super::super::{
+        Mpu6050, Mpu6050Config, Mpu6050ObserverData, AccelRange, GyroRange, DlpfBandwidth, OrientationFilter,
+        REG_INT_PIN_CFG, INT_PIN_CFG_RD_CLEAR, FIFO_EN_SAMPLE,
+    };
+
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32), tolerance: f32) {
+        assert!((actual.0 - expected.0).abs() < tolerance, "{:?} != {:?}", actual, expected);
+        assert!((actual.1 - expected.1).abs() < tolerance, "{:?} != {:?}", actual, expected);
+        assert!((actual.2 - expected.2).abs() < tolerance, "{:?} != {:?}", actual, expected);
+    }
+
+    fn config(accel_range: AccelRange, gyro_range: GyroRange) -> Mpu6050Config {
+        Mpu6050Config {
+            accel_range,
+            gyro_range,
+            dlpf: DlpfBandwidth::Hz44,
+            sample_rate_divider: 9,
+            fifo: true,
+            calibration_pause: Duration::ZERO,
+            ..Mpu6050Config::default()
+        }
+    }
+
+    #[test]
+    fn init_writes_configuration_and_removes_gyro_bias() {
+        let mut emulator = Mpu6050Emulator::new();
+        let sensor = emulator.clone();
+        sensor.set_gyro_bias((3f32, -2f32, 1f32));
+
+        let config = config(AccelRange::G8, GyroRange::Deg500);
+        let mut driver = Mpu6050::new(&mut emulator, Some(sensor.interrupt_pin()), None, config);
+        block_on(driver.init()).unwrap();
+
+        assert_eq!(sensor.register(REG_PWR_MGMT_1), 0x00);
+        assert_eq!(sensor.register(REG_SMPLRT_DIV), 9);
+        assert_eq!(sensor.register(REG_CONFIG), DlpfBandwidth::Hz44.register_value());
+        assert_eq!(sensor.register(REG_GYRO_CONFIG), GyroRange::Deg500.register_value());
+        assert_eq!(sensor.register(REG_ACCEL_CONFIG), AccelRange::G8.register_value());
+        assert_eq!(sensor.register(REG_INT_PIN_CFG), INT_PIN_CFG_RD_CLEAR);
+        assert_eq!(sensor.register(REG_INT_ENABLE), INT_DATA_RDY | INT_FIFO_OFLOW);
+        assert_eq!(sensor.register(REG_FIFO_EN), FIFO_EN_SAMPLE);
+        // FIFO_RESET cleared itself, the fifo keeps running
+        assert_eq!(sensor.register(REG_USER_CTRL), USER_CTRL_FIFO_EN);
+
+        let sample = block_on(driver.read_sample());
+        driver.handle_sample(sample);
+        assert_close(driver.gyro_vec, (0f32, 0f32, 0f32), 0.05);
+        assert_close(driver.acc_vec, (0f32, 0f32, 1f32), 0.05);
+    }
+
+    #[test]
+    fn samples_are_decoded_with_configured_ranges() {
+        let mut emulator = Mpu6050Emulator::new();
+        let sensor = emulator.clone();
+        // values outside of the default +-2g and 250deg/s ranges
+        sensor.set_gravity((0f32, 0.6f32, 0.8f32));
+        sensor.set_gyro_bias((300f32, -150f32, 20f32));
+        sensor.set_temperature(30f32);
+
+        let config = config(AccelRange::G16, GyroRange::Deg2000);
+        let mut driver = Mpu6050::<_, Mpu6050InterruptPin>::new(&mut emulator, None, None, config);
+        block_on(driver.init()).unwrap();
+
+        let sample = block_on(driver.read_sample());
+        assert_close(sample.acc, (0f32, 0.6f32, 0.8f32), 0.01);
+        assert_close(sample.gyro, (300f32, -150f32, 20f32), 0.1);
+        assert!((sample.temperature - 30f32).abs() < 0.01);
+
+        std::thread::sleep(Duration::from_millis(50));
+        let samples = block_on(driver.read_fifo());
+        assert!(!samples.is_empty());
+        for sample in samples {
+            assert_close(sample.acc, (0f32, 0.6f32, 0.8f32), 0.01);
+            assert_close(sample.gyro, (300f32, -150f32, 20f32), 0.1);
+        }
+    }
+
+    #[test]
+    fn fifo_recovers_from_overflow() {
+        let mut emulator = Mpu6050Emulator::new();
+        let config = Mpu6050Config {
+            dlpf: DlpfBandwidth::Hz184,
+            sample_rate_divider: 0,
+            ..config(AccelRange::G2, GyroRange::Deg250)
+        };
+        let mut driver = Mpu6050::<_, Mpu6050InterruptPin>::new(&mut emulator, None, None, config);
+        block_on(driver.init()).unwrap();
+
+        // 1024 bytes hold 73 samples, at 1 kHz the fifo overflows after 73 ms
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(block_on(driver.read_fifo()).is_empty());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(block_on(driver.read_fifo()).len() >= 15);
+
+        // the full fifo raises FIFO_OFLOW once more while it is being reset
+        std::thread::sleep(Duration::from_millis(100));
+        block_on(driver.reset_fifo()).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(block_on(driver.read_fifo()).len() >= 15);
+    }
+
+    // everything the driver publishes while the device turns by 90 deg around x and then rests
+    fn follow_script(filter: OrientationFilter) -> Vec<Mpu6050ObserverData> {
+        let mut emulator = Mpu6050Emulator::new();
+        let sensor = emulator.clone();
+        let (server, observer) = async_channel::unbounded();
+        let config = Mpu6050Config { filter, ..config(AccelRange::G2, GyroRange::Deg1000) };
+        let mut driver = Mpu6050::new(&mut emulator, Some(sensor.interrupt_pin()), Some(server), config);
+        block_on(driver.init()).unwrap();
+
+        sensor.set_script(vec![
+            MotionStep { rotation: (90f32, 0f32, 0f32), duration: Duration::from_secs(1) },
+            MotionStep { rotation: (0f32, 0f32, 0f32), duration: Duration::from_millis(1500) },
+        ]);
+        block_on(select(pin!(driver.run()), Delay::new(Duration::from_millis(2500))));
+        assert_close(sensor.gravity(), (0f32, 1f32, 0f32), 0.01);
+
+        std::iter::from_fn(|| observer.try_recv().ok()).collect()
+    }
+
+    fn assert_follows_script(published: Vec<Mpu6050ObserverData>) {
+        // the rate is published while turning and the orientation settles once the device rests
+        let max_rate = published.iter().map(|data| data.gyro_vec.0).fold(0f32, f32::max);
+        assert!((max_rate - 90f32).abs() < 1f32, "max rate {}", max_rate);
+
+        let last = published.last().unwrap();
+        assert_close(last.gyro_vec, (0f32, 0f32, 0f32), 0.1);
+        assert_close(last.gravity, (0f32, 1f32, 0f32), 0.05);
+        assert!((last.orientation.0 - 90f32).abs() < 3f32, "roll {}", last.orientation.0);
+    }
+
+    #[test]
+    fn complementary_filter_follows_script() {
+        assert_follows_script(follow_script(OrientationFilter::Complementary { coefficient: 0.96 }));
+    }
+
+    #[test]
+    fn madgwick_filter_follows_script() {
+        assert_follows_script(follow_script(OrientationFilter::Madgwick { beta: 0.5 }));
+    }
+
+    #[test]
+    fn data_ready_interrupt_wakes_the_driver() {
+        let mut emulator = Mpu6050Emulator::new();
+        let sensor = emulator.clone();
+        let config = config(AccelRange::G2, GyroRange::Deg250);
+        let mut driver = Mpu6050::new(&mut emulator, Some(sensor.interrupt_pin()), None, config);
+        block_on(driver.init()).unwrap();
+
+        // the pin pulses on the next sample, 10 ms apart at 100 Hz
+        for _ in 0..5 {
+            let start = Instant::now();
+            block_on(driver.wait_for_data());
+            assert!(start.elapsed() <= config.sample_period() + Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn missing_interrupt_times_out() {
+        let mut emulator = Mpu6050Emulator::new();
+        let sensor = emulator.clone();
+        let config = config(AccelRange::G2, GyroRange::Deg250);
+        let mut driver = Mpu6050::new(&mut emulator, Some(sensor.interrupt_pin()), None, config);
+        block_on(driver.init()).unwrap();
+        // data ready interrupt disabled, the pin never fires
+        block_on(driver.i2c.write(DEFAULT_ADDRESS, &[REG_INT_ENABLE, 0x00])).unwrap();
+
+        let start = Instant::now();
+        block_on(driver.wait_for_data());
+        let elapsed = start.elapsed();
+        assert!(elapsed >= 2 * config.sample_period(), "{:?}", elapsed);
+        assert!(elapsed < 3 * config.sample_period(), "{:?}", elapsed);
+    }
+}
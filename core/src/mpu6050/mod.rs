@@ -0,0 +1,412 @@
+use std::time::{Duration, Instant};
+use std::pin::pin;
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use crate::i2c::I2cTransportInterface;
+use crate::interrupt::InterruptPinInterface;
+use async_channel::Sender;
+
+mod config;
+pub use config::{AccelRange, DlpfBandwidth, GyroRange, Mpu6050Config, OrientationFilter};
+mod ahrs;
+use ahrs::Madgwick;
+mod sample;
+pub use sample::Mpu6050Sample;
+use sample::SAMPLE_SIZE;
+#[cfg(any(test, feature = "emulator"))]
+mod emulator;
+#[cfg(any(test, feature = "emulator"))]
+pub use emulator::{AddressNack, MotionStep, Mpu6050Emulator, Mpu6050InterruptPin};
+
+
+const DEFAULT_ADDRESS: u8 = 0x68;
+const REG_SMPLRT_DIV: u8 = 0x19;
+const REG_CONFIG: u8 = 0x1A;
+const REG_GYRO_CONFIG: u8 = 0x1B;
+const REG_ACCEL_CONFIG: u8 = 0x1C;
+const REG_FIFO_EN: u8 = 0x23;
+const REG_INT_PIN_CFG: u8 = 0x37;
+const REG_INT_ENABLE: u8 = 0x38;
+const REG_INT_STATUS: u8 = 0x3A;
+const REG_ACCEL_XOUT_H: u8 = 0x3B;
+const REG_USER_CTRL: u8 = 0x6A;
+const REG_PWR_MGMT_1: u8 = 0x6B;
+const REG_FIFO_COUNT_H: u8 = 0x72;
+const REG_FIFO_R_W: u8 = 0x74;
+const FIFO_SIZE: usize = 1024;
+// temperature, gyroscope x/y/z and accelerometer, stored in the same order as ACCEL_XOUT_H..GYRO_ZOUT_L
+const FIFO_EN_SAMPLE: u8 = 0xF8;
+const USER_CTRL_FIFO_EN: u8 = 0x40;
+const USER_CTRL_FIFO_RESET: u8 = 0x04;
+const INT_FIFO_OFLOW: u8 = 0x10;
+const INT_DATA_RDY: u8 = 0x01;
+// INT_RD_CLEAR, interrupt status is cleared by any read
+const INT_PIN_CFG_RD_CLEAR: u8 = 0x10;
+// period of reading the sensor when the INT pin is not connected
+const POLL_PERIOD: Duration = Duration::from_millis(10);
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Mpu6050ObserverData {
+    pub acc_vec: (f32, f32, f32),
+    pub acc_angle: (f32, f32, f32),
+    // roll, pitch and yaw in degrees fused from gyroscope and accelerometer
+    pub orientation: (f32, f32, f32),
+    // unit vector pointing up in the sensor frame, taken from the fused orientation
+    pub gravity: (f32, f32, f32),
+    // angular rate in deg/s
+    pub gyro_vec: (f32, f32, f32),
+    pub temperature: f32,
+}
+
+pub struct Mpu6050<'a, T: I2cTransportInterface, P: InterruptPinInterface> {
+    i2c: &'a mut T,
+    int_pin: Option<P>,
+    temperature: f32,
+    acc_vec: (f32, f32, f32),
+    acc_err: (f32, f32, f32),
+    acc_angle: (f32, f32, f32),
+    gyro_vec: (f32, f32, f32),
+    gyro_err: (f32, f32, f32),
+    orientation: (f32, f32, f32),
+    gravity: (f32, f32, f32),
+    ahrs: Option<Madgwick>,
+    read_time_prev: Instant,
+    // time of the last sample taken out of the fifo
+    fifo_time: Instant,
+    observer: Option<Sender<Mpu6050ObserverData>>,
+    config: Mpu6050Config,
+}
+
+pub async fn mpu6050_task<T, P>(mut i2c: T, int_pin: Option<P>, observer: Option<Sender<Mpu6050ObserverData>>, config: Mpu6050Config) 
+where
+    T: I2cTransportInterface,
+    P: InterruptPinInterface
+{
+    let mut this = Mpu6050::new(&mut i2c, int_pin, observer, config);
+
+    this.init().await.unwrap();
+    this.run().await;
+}
+
+impl<'a, T: I2cTransportInterface, P: InterruptPinInterface> Mpu6050<'a, T, P> {
+
+    // without the INT pin the sensor is polled every POLL_PERIOD
+    pub fn new(i2c: &'a mut T, int_pin: Option<P>, observer: Option<Sender<Mpu6050ObserverData>>, config: Mpu6050Config) -> Self {
+        Self { i2c,
+            int_pin,
+            temperature: 0f32,
+            acc_vec: (0f32,0f32,0f32),
+            acc_err: (0f32,0f32,0f32),
+            acc_angle: (0f32,0f32,0f32),
+            gyro_vec: (0f32,0f32,0f32),
+            gyro_err: (0f32,0f32,0f32),
+            orientation: (0f32,0f32,0f32),
+            gravity: (0f32,0f32,1f32),
+            ahrs: match config.filter {
+                OrientationFilter::Madgwick { beta } => Some(Madgwick::new(beta)),
+                OrientationFilter::Complementary { .. } => None,
+            },
+            read_time_prev: Instant::now(),
+            fifo_time: Instant::now(),
+            observer,
+            config,
+         }
+    }
+
+    pub async fn init(&mut self) -> Result<(), T::Error> {
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_PWR_MGMT_1, 0x00]).await?; // reset 
+
+        // filtering and output data rate
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_CONFIG, self.config.dlpf.register_value()]).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_SMPLRT_DIV, self.config.sample_rate_divider]).await?;
+
+        // full scale ranges, readings are converted with the matching sensitivity
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_ACCEL_CONFIG, self.config.accel_range.register_value()]).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_GYRO_CONFIG, self.config.gyro_range.register_value()]).await?;
+
+        self.calculate_error().await;
+
+        // INT pin pulses whenever a new sample is in the data registers and the fifo,
+        // fifo overflow is only reported in INT_STATUS when its interrupt is enabled
+        let mut int_enable = if self.int_pin.is_some() { INT_DATA_RDY } else { 0 };
+        if self.config.fifo {
+            int_enable |= INT_FIFO_OFLOW;
+        }
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_INT_PIN_CFG, INT_PIN_CFG_RD_CLEAR]).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_INT_ENABLE, int_enable]).await?;
+
+        if self.config.fifo {
+            self.reset_fifo().await?;
+            self.read_time_prev = self.fifo_time;
+        }
+
+        log::info!("Mpu6050 init done");
+
+        Ok(())
+    }
+
+    async fn calculate_error(&mut self) {
+        let max_iter = 200;
+        let mut acc_sum = (0f32, 0f32, 0f32);
+        let mut gyro_sum = (0f32, 0f32, 0f32);
+
+        for _ in 0..max_iter {
+            let sample = self.read_sample().await;
+            acc_sum.0 += sample.acc.0;
+            acc_sum.1 += sample.acc.1;
+            acc_sum.2 += sample.acc.2;
+            gyro_sum.0 += sample.gyro.0;
+            gyro_sum.1 += sample.gyro.1;
+            gyro_sum.2 += sample.gyro.2;
+        }
+        // assuming device lays on flat surface, x and y acceleration vectors should be 0 and z vector should be 1 (g)
+        self.acc_err.0 = 0f32 - acc_sum.0 / max_iter as f32;
+        self.acc_err.1 = 0f32 - acc_sum.1 / max_iter as f32;
+        self.acc_err.2 = 1f32 - acc_sum.2 / max_iter as f32;
+
+        self.gyro_err.0 = gyro_sum.0 / max_iter as f32;
+        self.gyro_err.1 = gyro_sum.1 / max_iter as f32;
+        self.gyro_err.2 = gyro_sum.2 / max_iter as f32;
+
+        println!("Accelerometer error: x={}, y={}, z={}", self.acc_err.0, self.acc_err.1, self.acc_err.2);
+        println!("Gyroscope error: x={}, y={}, z={}", self.gyro_err.0, self.gyro_err.1, self.gyro_err.2);
+        Delay::new(self.config.calibration_pause).await;
+        self.read_time_prev = Instant::now();
+    }
+
+    // clear the fifo and start collecting accelerometer, temperature and gyroscope samples,
+    // FIFO_RESET only takes effect while the fifo is disabled
+    async fn reset_fifo(&mut self) -> Result<(), T::Error> {
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_FIFO_EN, 0x00]).await?;
+        // a full fifo may have raised FIFO_OFLOW again since INT_STATUS was read, clear it
+        // now that no more samples are written, otherwise the next read drops a valid batch
+        let mut status = [0u8; 1];
+        self.i2c.write_read(DEFAULT_ADDRESS, &[REG_INT_STATUS], &mut status).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_USER_CTRL, USER_CTRL_FIFO_RESET]).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_USER_CTRL, USER_CTRL_FIFO_EN]).await?;
+        self.i2c.write(DEFAULT_ADDRESS, &[REG_FIFO_EN, FIFO_EN_SAMPLE]).await?;
+        self.fifo_time = Instant::now();
+        Ok(())
+    }
+
+    // all complete samples collected since the last call, timestamped one sample period apart
+    async fn read_fifo(&mut self) -> Vec<Mpu6050Sample> {
+        let mut status = [0u8; 1];
+        self.i2c.write_read(DEFAULT_ADDRESS, &[REG_INT_STATUS], &mut status).await.unwrap();
+        let mut count = [0u8; 2];
+        self.i2c.write_read(DEFAULT_ADDRESS, &[REG_FIFO_COUNT_H], &mut count).await.unwrap();
+        let count = u16::from_be_bytes(count) as usize;
+
+        if status[0] & INT_FIFO_OFLOW != 0 || count >= FIFO_SIZE {
+            // the oldest bytes were overwritten, so the fifo is no longer aligned to whole samples
+            log::warn!("Mpu6050 fifo overflow, samples dropped");
+            self.reset_fifo().await.unwrap();
+            return Vec::new();
+        }
+
+        let samples = count / SAMPLE_SIZE;
+        if samples == 0 {
+            return Vec::new();
+        }
+
+        let mut buf = vec![0u8; samples * SAMPLE_SIZE];
+        self.i2c.write_read(DEFAULT_ADDRESS, &[REG_FIFO_R_W], &mut buf).await.unwrap();
+
+        let period = self.config.sample_period();
+        buf.chunks_exact(SAMPLE_SIZE)
+            .map(|chunk| {
+                self.fifo_time += period;
+                Mpu6050Sample::decode(chunk.try_into().unwrap(), &self.config, self.fifo_time)
+            })
+            .collect()
+    }
+
+    // accelerometer, temperature and gyroscope registers in one burst starting at ACCEL_XOUT_H
+    async fn read_sample(&mut self) -> Mpu6050Sample {
+        let mut buf = [0u8; SAMPLE_SIZE];
+        self.i2c.write_read(DEFAULT_ADDRESS, &[REG_ACCEL_XOUT_H], &mut buf).await.unwrap();
+        Mpu6050Sample::decode(&buf, &self.config, Instant::now())
+    }
+
+    // integrate one sample into the accelerometer angles and the orientation
+    fn handle_sample(&mut self, sample: Mpu6050Sample) {
+        self.temperature = sample.temperature;
+        self.acc_vec.0 = sample.acc.0 + self.acc_err.0;
+        self.acc_vec.1 = sample.acc.1 + self.acc_err.1;
+        self.acc_vec.2 = sample.acc.2 + self.acc_err.2;
+        let acc = self.acc_vec;
+
+        let digi_places = 1;
+
+        self.acc_vec.0 = round(self.acc_vec.0, digi_places);
+        self.acc_vec.1 = round(self.acc_vec.1, digi_places);
+        self.acc_vec.2 = round(self.acc_vec.2, digi_places);
+
+        if self.acc_vec.2 == 0f32 {
+            self.acc_vec.2 = 0.00000001f32;
+        }
+        if self.acc_vec.0 == self.acc_vec.2 {
+            self.acc_vec.0 += 0.00000001f32;
+        }
+        if self.acc_vec.1 == self.acc_vec.2 {
+            self.acc_vec.1 += 0.00000001f32;
+        }
+
+        let acc_angle_x: f32 = round( (self.acc_vec.1 / (self.acc_vec.0.powf(2f32) + self.acc_vec.2.powf(2f32)).sqrt()).atan() * 180f32 / std::f32::consts::PI, digi_places);
+        let acc_angle_y: f32 = round( (self.acc_vec.0 / (self.acc_vec.1.powf(2f32) + self.acc_vec.2.powf(2f32)).sqrt()).atan() * 180f32 / std::f32::consts::PI, digi_places);
+        let acc_angle_z: f32 = round( ((self.acc_vec.0.powf(2f32) + self.acc_vec.1.powf(2f32)).sqrt() / self.acc_vec.2 ).atan() * 180f32 / std::f32::consts::PI, digi_places);
+
+        self.acc_angle.0 = acc_angle_x;
+        self.acc_angle.1 = acc_angle_y;
+        self.acc_angle.2 = acc_angle_z;
+
+        // fifo samples are evenly spaced by the sample period, polled ones carry the read time
+        let delta_time = sample.time.saturating_duration_since(self.read_time_prev).as_secs_f32();
+        self.read_time_prev = sample.time;
+
+        self.gyro_vec.0 = sample.gyro.0 - self.gyro_err.0;
+        self.gyro_vec.1 = sample.gyro.1 - self.gyro_err.1;
+        self.gyro_vec.2 = sample.gyro.2 - self.gyro_err.2;
+
+        if let Some(ahrs) = &mut self.ahrs {
+            // unrounded acceleration, the filter smooths it itself
+            ahrs.update(self.gyro_vec, acc, delta_time);
+            self.orientation = ahrs.euler();
+            self.gravity = ahrs.gravity();
+        } else if let OrientationFilter::Complementary { coefficient: k } = self.config.filter {
+            // complementary filter: integrated gyroscope is precise short term, accelerometer angles
+            // don't drift, yaw can't be corrected by gravity so it is integrated only. Roll and pitch
+            // of the measured gravity are Euler angles like the ones of the Madgwick filter, roll
+            // covers the full circle so it is blended along the shorter way around.
+            // Angles come from the unrounded acceleration, rounding would quantize the blend.
+            let roll = acc.1.atan2(acc.2).to_degrees();
+            let pitch = (-acc.0).atan2(acc.1.hypot(acc.2)).to_degrees();
+            let gyro_roll = self.orientation.0 + self.gyro_vec.0 * delta_time;
+            let gyro_pitch = self.orientation.1 + self.gyro_vec.1 * delta_time;
+            self.orientation.0 = wrap_degrees(gyro_roll + (1f32 - k) * wrap_degrees(roll - gyro_roll));
+            self.orientation.1 = k * gyro_pitch + (1f32 - k) * pitch;
+            self.orientation.2 += self.gyro_vec.2 * delta_time;
+
+            let (sin_roll, cos_roll) = self.orientation.0.to_radians().sin_cos();
+            let (sin_pitch, cos_pitch) = self.orientation.1.to_radians().sin_cos();
+            self.gravity = (-sin_pitch, sin_roll * cos_pitch, cos_roll * cos_pitch);
+        }
+    }
+
+    fn observer_data(&self) -> Mpu6050ObserverData {
+        Mpu6050ObserverData {
+            acc_vec: self.acc_vec,
+            acc_angle: self.acc_angle,
+            orientation: self.orientation,
+            gravity: self.gravity,
+            gyro_vec: self.gyro_vec,
+            temperature: self.temperature,
+        }
+    }
+
+    // sleep until the sensor signals a new sample or poll when there is no INT pin
+    async fn wait_for_data(&mut self) {
+        // a missed interrupt must not stall the sensor, so the pin is raced against two sample periods
+        let timeout = 2 * self.config.sample_period();
+        match &mut self.int_pin {
+            Some(pin) => match select(pin!(pin.wait_for_interrupt()), Delay::new(timeout)).await {
+                Either::Left((result, _)) => result.unwrap(),
+                Either::Right(_) => log::warn!("Mpu6050 interrupt timeout, reading without it"),
+            },
+            None => Delay::new(POLL_PERIOD).await,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        log::info!("Mpu6050 started");
+
+        let mut print_time = Instant::now();
+        let mut old_data = self.observer_data();
+
+        loop {
+            let samples = if self.config.fifo {
+                self.read_fifo().await
+            } else {
+                vec![self.read_sample().await]
+            };
+            for sample in samples {
+                self.handle_sample(sample);
+            }
+
+            let current_time = Instant::now();
+            if current_time.saturating_duration_since(print_time).as_millis() > 500 {
+                print_time = current_time;
+                //log::info!("temperature:       {}", self.temperature);
+                //log::info!("accelerometer:   v = ( {:1.1} , {:1.1} , {:1.1} )   ang = ( {:1.1} , {:1.1} , {:1.1} )", self.acc_vec.0, self.acc_vec.1, self.acc_vec.2, self.acc_angle.0, self.acc_angle.1, self.acc_angle.2);
+                //log::info!("gyroscope:       ( {} , {} , {} )", self.gyro_vec.0, self.gyro_vec.1, self.gyro_vec.2);
+                //log::info!("roll/pitch/yaw:  ( {} , {} , {} )\n", self.orientation.0, self.orientation.1, self.orientation.2);
+            }
+
+            if let Some(observer) = &self.observer {
+                let new_data = self.observer_data();
+                if old_data != new_data { // todo: compare only up to 0.1
+                    old_data = new_data;
+                    observer.send(new_data).await.unwrap();
+                }
+            }
+            self.wait_for_data().await;
+        }
+
+    }
+}
+
+
+fn round(x: f32, decimals: u32) -> f32 {
+    let y = 10i32.pow(decimals) as f32;
+    (x * y).round() / y
+}
+
+// angle in degrees mapped to -180..180
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180f32).rem_euclid(360f32) - 180f32
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // gravity published by the complementary filter after it settled on a constant acceleration
+    fn complementary_gravity(acc: (f32, f32, f32)) -> (f32, f32, f32) {
+        let mut emulator = Mpu6050Emulator::new();
+        let mut driver = Mpu6050::<_, Mpu6050InterruptPin>::new(&mut emulator, None, None, Mpu6050Config::default());
+        let start = Instant::now();
+        for i in 1..500 {
+            let time = start + Duration::from_millis(10 * i);
+            driver.handle_sample(Mpu6050Sample { time, acc, temperature: 25f32, gyro: (0f32, 0f32, 0f32) });
+        }
+        driver.gravity
+    }
+
+    fn assert_close(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let error = (actual.0 - expected.0).abs().max((actual.1 - expected.1).abs()).max((actual.2 - expected.2).abs());
+        assert!(error < 0.01, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn complementary_gravity_follows_acceleration() {
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        for acc in [
+            (0f32, 0f32, 1f32),
+            (-s, s, 0f32),
+            (0f32, 0f32, -1f32),
+            (0f32, -s, -s),
+            (s, 0f32, -s),
+            (0.6f32, 0f32, 0.8f32),
+        ] {
+            assert_close(complementary_gravity(acc), acc);
+        }
+    }
+
+    #[test]
+    fn roll_is_blended_across_the_wrap_around() {
+        assert_eq!(wrap_degrees(190f32), -170f32);
+        assert_eq!(wrap_degrees(-190f32), 170f32);
+        assert_eq!(wrap_degrees(45f32), 45f32);
+    }
+}
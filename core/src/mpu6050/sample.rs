@@ -0,0 +1,37 @@
+use std::time::Instant;
+use super::Mpu6050Config;
+
+
+// number of bytes of the ACCEL_XOUT_H (0x3B) .. GYRO_ZOUT_L (0x48) register block
+pub const SAMPLE_SIZE: usize = 14;
+
+
+// One reading of all sensors taken in a single transaction, so the values belong together in time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mpu6050Sample {
+    pub time: Instant,
+    // acceleration in g
+    pub acc: (f32, f32, f32),
+    // temperature in degrees Celsius
+    pub temperature: f32,
+    // angular rate in deg/s
+    pub gyro: (f32, f32, f32),
+}
+
+impl Mpu6050Sample {
+
+    // raw values are divided by the sensitivity of the configured ranges, e.g. 16384 LSB/g for +-2g
+    // and 131 LSB/(deg/s) for 250deg/s
+    pub fn decode(buf: &[u8; SAMPLE_SIZE], config: &Mpu6050Config, time: Instant) -> Self {
+        let word = |i: usize| ((buf[i] as i16) << 8 | (buf[i + 1] as i16)) as f32;
+        let acc_sensitivity = config.accel_range.sensitivity();
+        let gyro_sensitivity = config.gyro_range.sensitivity();
+
+        Self {
+            time,
+            acc: (word(0) / acc_sensitivity, word(2) / acc_sensitivity, word(4) / acc_sensitivity),
+            temperature: word(6) / 340_f32 + 36.53_f32,
+            gyro: (word(8) / gyro_sensitivity, word(10) / gyro_sensitivity, word(12) / gyro_sensitivity),
+        }
+    }
+}
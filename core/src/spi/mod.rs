@@ -0,0 +1,10 @@
+use std::fmt::Debug;
+
+
+#[allow(async_fn_in_trait)]
+pub trait SpiTransportInterface {
+    type Error: Debug;
+
+    async fn write(&mut self, _data: &[u8]) -> Result<(), Self::Error> { Ok(()) }
+    async fn read(&mut self, _data: &[u8]) -> Result<(), Self::Error> { Ok(()) }
+}
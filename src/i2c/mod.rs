@@ -4,19 +4,7 @@ use esp_idf_hal::peripheral::Peripheral;
 use esp_idf_hal::prelude::*;
 use esp_idf_sys::EspError;
 use esp_idf_hal::delay::BLOCK;
-
-
-
-pub trait I2cTransportInterface {
-
-    // read up to output len
-    async fn write_read(&mut self, address: u8, data_to_write: &[u8], output: &mut [u8]) -> Result<(), EspError>;
-
-    async fn write(&mut self, address: u8, data: &[u8]) -> Result<(), EspError>;
-
-    // read up to output len
-    async fn read(&mut self, address: u8, output: &mut [u8]) -> Result<(), EspError>;
-}
+use led_hourglass_core::i2c::I2cTransportInterface;
 
 
 pub struct I2cInterface<'a> {
@@ -39,6 +27,7 @@ impl<'a> I2cInterface<'a> {
 }
 
 impl<'a> I2cTransportInterface for I2cInterface<'a> {
+    type Error = EspError;
 
     async fn write_read(&mut self, address: u8, data_to_write: &[u8], output: &mut [u8]) -> Result<(), EspError> {
         self.i2c.write_read(address, &data_to_write, output, BLOCK)
@@ -0,0 +1,29 @@
+use esp_idf_hal::gpio::{AnyIOPin, Input, PinDriver, Pull};
+use esp_idf_sys::EspError;
+use led_hourglass_core::interrupt::InterruptPinInterface;
+
+
+pub struct InterruptPin<'a> {
+    pin: PinDriver<'a, AnyIOPin, Input>,
+}
+
+impl<'a> InterruptPin<'a> {
+
+    // active high push-pull interrupt output, pulled down so an unconnected pin stays idle
+    pub fn init(gpio: AnyIOPin) -> Result<Self, EspError> {
+        let mut pin = PinDriver::input(gpio)?;
+        pin.set_pull(Pull::Down)?;
+
+        log::info!("Interrupt pin started");
+
+        Ok(Self { pin })
+    }
+}
+
+impl<'a> InterruptPinInterface for InterruptPin<'a> {
+    type Error = EspError;
+
+    async fn wait_for_interrupt(&mut self) -> Result<(), EspError> {
+        self.pin.wait_for_rising_edge().await
+    }
+}
@@ -2,19 +2,22 @@ use esp_idf_sys::{self as _}; // If using the `binstart` feature of `esp-idf-sys
 use edge_executor::Executor;
 use esp_idf_hal::gpio::*;
 use esp_idf_hal::peripherals::Peripherals;
+use std::time::Duration;
 
 mod led_heartbeat;
 use led_heartbeat::*;
-mod max7219;
-use max7219::*;
 mod mpu6050;
-use mpu6050::*;
 mod spi;
 mod i2c;
-mod logic;
-use logic::*;
+mod interrupt;
+use led_hourglass_core::max7219::*;
+use led_hourglass_core::mpu6050::*;
+use led_hourglass_core::logic::*;
 
 
+// number of cascaded MAX7219 led matrices
+const MAX7219_DEVICES: usize = 2;
+
 
 async fn app<'a>(rt: &Executor<'a>) {
     log::info!("App started");
@@ -34,6 +37,8 @@ async fn app<'a>(rt: &Executor<'a>) {
     let scl = peripherals.pins.gpio22;
     let i2c = peripherals.i2c0;
     let i2c_master = i2c::I2cInterface::init(i2c, sda.into(), scl.into()).unwrap();
+    // MPU6050 INT pin, None::<interrupt::InterruptPin> polls the sensor when it is not connected
+    let mpu6050_int = Some(interrupt::InterruptPin::init(peripherals.pins.gpio19.into()).unwrap());
 
     // create communication channels between tasks
     let (acc_server, acc_observer) = async_channel::unbounded::<Mpu6050ObserverData>();
@@ -44,13 +49,33 @@ async fn app<'a>(rt: &Executor<'a>) {
     let task1 = rt.spawn(led_heartbeat_task(led));
 
     // Setup logic task 
-    let task2 = rt.spawn(logic_task(acc_observer, led_matrix_client));
+    let logic_config = LogicConfig { duration: Duration::from_secs(3 * 60), flip_mode: FlipMode::Continue };
+    let task2 = rt.spawn(logic_task(acc_observer, led_matrix_client, logic_config));
 
     // Setup max7219 task 
-    let task3 = rt.spawn(max7219_task(spi_interface, Some(led_matrix_server)));
+    // orientation of the matrices as mounted on the board
+    let max7219_config = Max7219Config {
+        transforms: [Transform { rotation: Rotation::Deg0, flip_x: false, flip_y: false }; MAX7219_DEVICES],
+        refresh: RefreshPolicy::Every(Duration::from_secs(30)),
+        gray_slot: Duration::from_millis(10),
+    };
+    let task3 = rt.spawn(max7219_task(spi_interface, Some(led_matrix_server), max7219_config));
 
     // Setup mpu6050 task
-    let task4 = rt.spawn(mpu6050_task(i2c_master, Some(acc_server)));
+    // tilt of a hand held hourglass stays within +-2g, but a quick flip turns faster than 250 deg/s,
+    // 44 Hz filter and 1 kHz / (1 + 9) = 100 Hz output rate match the 10 ms polling loop,
+    // the fifo keeps samples which arrive while the task is delayed, higher than usual beta
+    // makes the orientation follow the accelerometer quickly once the hourglass is put down
+    let mpu6050_config = Mpu6050Config {
+        accel_range: AccelRange::G2,
+        gyro_range: GyroRange::Deg1000,
+        dlpf: DlpfBandwidth::Hz44,
+        sample_rate_divider: 9,
+        fifo: true,
+        filter: OrientationFilter::Madgwick { beta: 0.5 },
+        calibration_pause: Duration::from_secs(2),
+    };
+    let task4 = rt.spawn(mpu6050_task(i2c_master, mpu6050_int, Some(acc_server), mpu6050_config));
 
     // Start all task and wait until finished
     futures::join!(task1, task2, task3, task4);
@@ -5,13 +5,9 @@ use esp_idf_svc::hal::gpio;
 use esp_idf_hal::spi::config::*;
 use esp_idf_svc::hal::peripheral::Peripheral;
 use esp_idf_svc::hal::gpio::AnyIOPin;
+use led_hourglass_core::spi::SpiTransportInterface;
 
 
-pub trait SpiTransportInterface {
-    async fn write(&mut self, _data: &[u8]) -> Result<(), EspError> { Ok(()) }
-    async fn read(&mut self, _data: &[u8]) -> Result<(), EspError> { Ok(()) }
-}
-
 pub struct SpiInterface<'a> {
     spi: SpiDeviceDriver<'a, SpiDriver<'a>>,
 }
@@ -42,6 +38,7 @@ impl<'a> SpiInterface<'a> {
 }
 
 impl<'a> SpiTransportInterface for SpiInterface<'a> {
+    type Error = EspError;
 
     async fn write(&mut self, data: &[u8]) -> Result<(), EspError> {
         // todo self.spi.write_async(&data).await;